@@ -63,6 +63,13 @@ fn display_summary(summaries: &[Summary]) {
                 error = s.to_string();
                 String::from("...")
             }
+            Status::Cancelled => String::from("🛑"),
+            Status::TimedOut(kind) => {
+                error = kind.to_string();
+                String::from("⏱️")
+            }
+            Status::Verified => String::from("✅"),
+            Status::Missing => String::from("❓"),
         };
         table.add_row(vec![
             &s.download().filename,
@@ -0,0 +1,364 @@
+//! Explicitly typed checksums.
+//!
+//! A [`Checksum`] pairs a digest with the [`Algorithm`] that produced it, so
+//! that nothing has to be guessed from the format of the digest. Checksums are
+//! usually written as `<algorithm>:<hex digest>`, e.g. `sha256:9f86d0...`.
+//!
+//! # Supported Algorithms
+//!
+//! | Algorithm             | Names               | Digest              |
+//! |-----------------------|---------------------|---------------------|
+//! | [`Algorithm::Md5`]    | `md5`               | 16 bytes            |
+//! | [`Algorithm::Sha1`]   | `sha1`, `sha-1`     | 20 bytes            |
+//! | [`Algorithm::Sha256`] | `sha256`, `sha-256` | 32 bytes            |
+//! | [`Algorithm::Sha512`] | `sha512`, `sha-512` | 64 bytes            |
+//! | [`Algorithm::Blake3`] | `blake3`            | 32 bytes            |
+//! | [`Algorithm::Xxh64`]  | `xxh64`             | 8 bytes, big-endian |
+//! | [`Algorithm::Xxh3`]   | `xxh3`, `xxh3-64`   | 8 bytes, big-endian |
+//! | [`Algorithm::Crc32`]  | `crc32`             | 4 bytes, big-endian |
+//!
+//! CRC32 values may also be written in decimal. Since a decimal value of
+//! 8 digits cannot be told apart from a hex one, such values are read as hex,
+//! the usual notation, and hex values may be prefixed with `0x`.
+//!
+//! The untyped MD5 and CRC32 strings of [`Download::hash`](super::Download::hash)
+//! are still accepted through [`Checksum::guess`], as a legacy fallback.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use trauma::download::checksum::{Algorithm, Checksum};
+//!
+//! let checksum: Checksum =
+//!     "sha256:9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08".parse()?;
+//! assert_eq!(checksum.algorithm, Algorithm::Sha256);
+//!
+//! // Compute a checksum incrementally.
+//! let mut hasher = Algorithm::Sha256.hasher();
+//! hasher.update(b"test");
+//! assert_eq!(hasher.finalize(), checksum);
+//! # Ok::<(), trauma::Error>(())
+//! ```
+
+use super::hash::{detect_hash_type, HashType};
+use crate::error::Error;
+
+use sha2::Digest;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Size of the buffer used to hash files.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Hash algorithm of a [`Checksum`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    /// MD5.
+    Md5,
+    /// SHA-1.
+    Sha1,
+    /// SHA-256.
+    Sha256,
+    /// SHA-512.
+    Sha512,
+    /// BLAKE3, with its default 32 bytes output.
+    Blake3,
+    /// 64 bits xxHash.
+    Xxh64,
+    /// 64 bits XXH3.
+    Xxh3,
+    /// CRC-32 (IEEE).
+    Crc32,
+}
+
+impl Algorithm {
+    /// Get the canonical name of the algorithm.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Md5 => "md5",
+            Self::Sha1 => "sha1",
+            Self::Sha256 => "sha256",
+            Self::Sha512 => "sha512",
+            Self::Blake3 => "blake3",
+            Self::Xxh64 => "xxh64",
+            Self::Xxh3 => "xxh3",
+            Self::Crc32 => "crc32",
+        }
+    }
+
+    /// Get the length of the digests of the algorithm, in bytes.
+    pub fn digest_len(self) -> usize {
+        match self {
+            Self::Md5 => 16,
+            Self::Sha1 => 20,
+            Self::Sha256 | Self::Blake3 => 32,
+            Self::Sha512 => 64,
+            Self::Xxh64 | Self::Xxh3 => 8,
+            Self::Crc32 => 4,
+        }
+    }
+
+    /// Start computing a checksum with this algorithm.
+    pub fn hasher(self) -> Hasher {
+        let state = match self {
+            Self::Md5 => State::Md5(md5::Md5::new()),
+            Self::Sha1 => State::Sha1(sha1::Sha1::new()),
+            Self::Sha256 => State::Sha256(sha2::Sha256::new()),
+            Self::Sha512 => State::Sha512(sha2::Sha512::new()),
+            Self::Blake3 => State::Blake3(Box::default()),
+            Self::Xxh64 => State::Xxh64(xxhash_rust::xxh64::Xxh64::new(0)),
+            Self::Xxh3 => State::Xxh3(Box::default()),
+            Self::Crc32 => State::Crc32(crc32fast::Hasher::new()),
+        };
+        Hasher { state }
+    }
+
+    /// Rank the algorithm against the others, the strongest highest.
+    pub(crate) fn strength(self) -> u8 {
+        match self {
+            Self::Crc32 => 0,
+            Self::Xxh64 | Self::Xxh3 => 1,
+            Self::Md5 => 2,
+            Self::Sha1 => 3,
+            Self::Sha256 | Self::Blake3 => 4,
+            Self::Sha512 => 5,
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = Error;
+
+    /// Parse the name of an algorithm, ignoring its case.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "md5" => Ok(Self::Md5),
+            "sha1" | "sha-1" => Ok(Self::Sha1),
+            "sha256" | "sha-256" => Ok(Self::Sha256),
+            "sha512" | "sha-512" => Ok(Self::Sha512),
+            "blake3" => Ok(Self::Blake3),
+            "xxh64" => Ok(Self::Xxh64),
+            "xxh3" | "xxh3-64" => Ok(Self::Xxh3),
+            "crc32" => Ok(Self::Crc32),
+            _ => Err(Error::InvalidChecksum {
+                checksum: name.into(),
+                cause: "unsupported algorithm".into(),
+            }),
+        }
+    }
+}
+
+/// A digest along with the algorithm that produced it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Checksum {
+    /// Algorithm of the digest.
+    pub algorithm: Algorithm,
+    /// The digest, as bytes.
+    pub value: Vec<u8>,
+}
+
+impl Checksum {
+    /// Create a checksum from a digest written in hex.
+    ///
+    /// CRC32 digests may also be written in decimal.
+    pub fn from_hex(algorithm: Algorithm, digest: &str) -> Result<Self, Error> {
+        let invalid = |cause: &str| Error::InvalidChecksum {
+            checksum: format!("{}:{}", algorithm, digest).into(),
+            cause: cause.into(),
+        };
+
+        let digest = digest.trim();
+        let value = match algorithm {
+            Algorithm::Crc32 => parse_crc32(digest)
+                .ok_or_else(|| invalid("not a hex or decimal CRC32"))?
+                .to_be_bytes()
+                .to_vec(),
+            _ => decode_hex(digest).ok_or_else(|| invalid("not a hex digest"))?,
+        };
+        if value.len() != algorithm.digest_len() {
+            return Err(invalid("wrong digest length"));
+        }
+
+        Ok(Self { algorithm, value })
+    }
+
+    /// Guess the checksum of an untyped MD5 or CRC32 string.
+    ///
+    /// This is the legacy format of [`Download::hash`](super::Download::hash):
+    /// 32 hex characters are an MD5 digest and decimal numbers are a CRC32.
+    pub fn guess(hash: &str) -> Option<Self> {
+        match detect_hash_type(hash)? {
+            HashType::Md5 => Self::from_hex(Algorithm::Md5, hash).ok(),
+            HashType::Crc32 => Some(Self {
+                algorithm: Algorithm::Crc32,
+                value: hash.parse::<u32>().ok()?.to_be_bytes().to_vec(),
+            }),
+        }
+    }
+
+    /// Compute the checksum of the file at `path`.
+    pub fn compute(algorithm: Algorithm, path: &Path) -> io::Result<Self> {
+        let mut hasher = algorithm.hasher();
+        hasher.update_from_file(path, u64::MAX)?;
+        Ok(hasher.finalize())
+    }
+
+    /// Check whether the file at `path` has this checksum.
+    ///
+    /// Returns false if the file does not exist.
+    pub fn verify(&self, path: &Path) -> io::Result<bool> {
+        if !path.exists() {
+            return Ok(false);
+        }
+        Ok(Self::compute(self.algorithm, path)? == *self)
+    }
+
+    /// Get the digest written in lowercase hex.
+    pub fn to_hex(&self) -> String {
+        self.value.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+impl fmt::Display for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.algorithm, self.to_hex())
+    }
+}
+
+impl FromStr for Checksum {
+    type Err = Error;
+
+    /// Parse a checksum written as `<algorithm>:<digest>`.
+    fn from_str(checksum: &str) -> Result<Self, Self::Err> {
+        let (algorithm, digest) =
+            checksum
+                .split_once(':')
+                .ok_or_else(|| Error::InvalidChecksum {
+                    checksum: checksum.into(),
+                    cause: "expected <algorithm>:<digest>".into(),
+                })?;
+        Self::from_hex(algorithm.trim().parse()?, digest)
+    }
+}
+
+/// Computes a [`Checksum`] from data fed in chunks.
+pub struct Hasher {
+    /// State of the algorithm.
+    state: State,
+}
+
+/// Running state of each algorithm.
+enum State {
+    Md5(md5::Md5),
+    Sha1(sha1::Sha1),
+    Sha256(sha2::Sha256),
+    Sha512(sha2::Sha512),
+    Blake3(Box<blake3::Hasher>),
+    Xxh64(xxhash_rust::xxh64::Xxh64),
+    Xxh3(Box<xxhash_rust::xxh3::Xxh3>),
+    Crc32(crc32fast::Hasher),
+}
+
+impl Hasher {
+    /// Get the algorithm of the checksum being computed.
+    pub fn algorithm(&self) -> Algorithm {
+        match self.state {
+            State::Md5(_) => Algorithm::Md5,
+            State::Sha1(_) => Algorithm::Sha1,
+            State::Sha256(_) => Algorithm::Sha256,
+            State::Sha512(_) => Algorithm::Sha512,
+            State::Blake3(_) => Algorithm::Blake3,
+            State::Xxh64(_) => Algorithm::Xxh64,
+            State::Xxh3(_) => Algorithm::Xxh3,
+            State::Crc32(_) => Algorithm::Crc32,
+        }
+    }
+
+    /// Feed `data` to the hasher.
+    pub fn update(&mut self, data: &[u8]) {
+        match self.state {
+            State::Md5(ref mut h) => h.update(data),
+            State::Sha1(ref mut h) => h.update(data),
+            State::Sha256(ref mut h) => h.update(data),
+            State::Sha512(ref mut h) => h.update(data),
+            State::Blake3(ref mut h) => {
+                h.update(data);
+            }
+            State::Xxh64(ref mut h) => h.update(data),
+            State::Xxh3(ref mut h) => h.update(data),
+            State::Crc32(ref mut h) => h.update(data),
+        }
+    }
+
+    /// Feed at most the first `len` bytes of the file at `path` to the hasher.
+    ///
+    /// The file is read synchronously, keep it off the async threads.
+    pub fn update_from_file(&mut self, path: &Path, len: u64) -> io::Result<()> {
+        let mut file = File::open(path)?.take(len);
+        let mut buffer = vec![0; BUFFER_SIZE];
+        loop {
+            match file.read(&mut buffer)? {
+                0 => return Ok(()),
+                n => self.update(&buffer[..n]),
+            }
+        }
+    }
+
+    /// Get the checksum of all the data fed so far.
+    pub fn finalize(self) -> Checksum {
+        let algorithm = self.algorithm();
+        let value = match self.state {
+            State::Md5(h) => h.finalize().to_vec(),
+            State::Sha1(h) => h.finalize().to_vec(),
+            State::Sha256(h) => h.finalize().to_vec(),
+            State::Sha512(h) => h.finalize().to_vec(),
+            State::Blake3(h) => h.finalize().as_bytes().to_vec(),
+            State::Xxh64(h) => h.digest().to_be_bytes().to_vec(),
+            State::Xxh3(h) => h.digest().to_be_bytes().to_vec(),
+            State::Crc32(h) => h.finalize().to_be_bytes().to_vec(),
+        };
+        Checksum { algorithm, value }
+    }
+}
+
+impl fmt::Debug for Hasher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hasher")
+            .field("algorithm", &self.algorithm())
+            .finish()
+    }
+}
+
+/// Parse a CRC32 written in hex or decimal.
+fn parse_crc32(digest: &str) -> Option<u32> {
+    let is_hex = |s: &str| (1..=8).contains(&s.len()) && s.bytes().all(|b| b.is_ascii_hexdigit());
+    match digest
+        .strip_prefix("0x")
+        .or_else(|| digest.strip_prefix("0X"))
+    {
+        Some(hex) if is_hex(hex) => u32::from_str_radix(hex, 16).ok(),
+        None if digest.len() == 8 && is_hex(digest) => u32::from_str_radix(digest, 16).ok(),
+        None if digest.bytes().all(|b| b.is_ascii_digit()) => digest.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Decode a hex string.
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
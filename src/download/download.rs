@@ -46,16 +46,18 @@
 //! # }
 //! ```
 
+use super::checksum::Checksum;
 use crate::error::Error;
 
 use reqwest::{
     header::{ACCEPT_RANGES, CONTENT_LENGTH},
-    Url,
+    Response, Url,
 };
 use reqwest_middleware::ClientWithMiddleware;
 use std::convert::TryFrom;
 use std::error;
 use std::path::Path;
+use std::time::Duration;
 
 /// Represents a file to be downloaded.
 #[derive(Debug, Clone)]
@@ -65,9 +67,32 @@ pub struct Download {
     /// File name used to save the file on disk.
     pub filename: String,
     /// Hash of the file (MD5 or CRC32).
+    ///
+    /// The algorithm is guessed from the format of the hash, unless it is
+    /// written as a typed [`Checksum`]. Prefer [`Download::checksum`].
     pub hash: Option<String>,
+    /// Checksum of the file, taking precedence over [`Download::hash`].
+    pub checksum: Option<Checksum>,
+    /// Expected size of the file, in bytes, if known in advance.
+    ///
+    /// It spares probing the server for the size, and a server announcing
+    /// another size fails the download.
+    pub size: Option<u64>,
     /// Target file to extract from archives
     pub target_file: Option<String>,
+    /// Bandwidth limit of this download, in bytes per second.
+    ///
+    /// It applies on top of the limit shared by the whole batch.
+    pub max_bandwidth: Option<u64>,
+    /// Scheduling priority, downloads with a higher priority start first.
+    pub priority: i32,
+    /// Idle timeout of this download, overriding the one of the downloader.
+    pub idle_timeout: Option<Duration>,
+    /// Deadline of this download, overriding the one of the downloader.
+    pub deadline: Option<Duration>,
+    /// Other URLs serving the same file, tried in order when the previous
+    /// ones fail.
+    pub mirrors: Vec<Url>,
 }
 
 impl Download {
@@ -97,14 +122,41 @@ impl Download {
             url,
             filename: String::from(filename),
             hash: None,
+            checksum: None,
+            size: None,
             target_file: None,
+            max_bandwidth: None,
+            priority: 0,
+            idle_timeout: None,
+            deadline: None,
+            mirrors: Vec::new(),
         }
     }
 
     /// Calculate hash of local file and compare with expected hash.
     /// Returns true if hashes match or if no hash is provided.
     pub fn verify_hash(&self, file_path: &Path) -> Result<bool, Box<dyn error::Error>> {
-        super::hash::verify_hash(file_path, self.hash.as_ref())
+        match self.checksum {
+            Some(ref checksum) => Ok(checksum.verify(file_path)?),
+            None => super::hash::verify_hash(file_path, self.hash.as_ref()),
+        }
+    }
+
+    /// Get the checksum the file is verified against, if it is known.
+    ///
+    /// This is the [`checksum`](Self::checksum), or else the typed or guessed
+    /// [`hash`](Self::hash).
+    pub fn expected_checksum(&self) -> Option<Checksum> {
+        match (&self.checksum, &self.hash) {
+            (Some(checksum), _) => Some(checksum.clone()),
+            (None, Some(hash)) => hash.parse().ok().or_else(|| Checksum::guess(hash)),
+            (None, None) => None,
+        }
+    }
+
+    /// Check whether the file has a checksum or a hash to verify.
+    pub fn has_hash(&self) -> bool {
+        self.checksum.is_some() || self.hash.is_some()
     }
 
     /// Check whether the download is resumable.
@@ -112,13 +164,22 @@ impl Download {
         &self,
         client: &ClientWithMiddleware,
     ) -> Result<bool, reqwest_middleware::Error> {
+        self.probe(client).await.map(|(resumable, _)| resumable)
+    }
+
+    /// Check whether the download is resumable, along with the response
+    /// describing the remote file, such as its validators and its name.
+    pub(crate) async fn probe(
+        &self,
+        client: &ClientWithMiddleware,
+    ) -> Result<(bool, Response), reqwest_middleware::Error> {
         let res = client.head(self.url.clone()).send().await?;
-        let headers = res.headers();
-        match headers.get(ACCEPT_RANGES) {
-            None => Ok(false),
-            Some(x) if x == "none" => Ok(false),
-            Some(_) => Ok(true),
-        }
+        let resumable = match res.headers().get(ACCEPT_RANGES) {
+            None => false,
+            Some(x) if x == "none" => false,
+            Some(_) => true,
+        };
+        Ok((resumable, res))
     }
 
     /// Retrieve the content_length of the download.
@@ -155,6 +216,20 @@ impl Download {
         self.target_file.as_deref()
     }
 
+    /// Get the URL of the download followed by its mirrors.
+    pub fn urls(&self) -> impl Iterator<Item = &Url> {
+        std::iter::once(&self.url).chain(self.mirrors.iter())
+    }
+
+    /// Copy this download, fetching it from `url` with the other URLs as mirrors.
+    pub(crate) fn with_url(&self, url: &Url) -> Download {
+        Download {
+            url: url.clone(),
+            mirrors: self.urls().filter(|u| *u != url).cloned().collect(),
+            ..self.clone()
+        }
+    }
+
 }
 
 impl TryFrom<&Url> for Download {
@@ -177,7 +252,14 @@ impl TryFrom<&Url> for Download {
                     .map(|(key, val)| [key, val].concat())
                     .collect(),
                 hash: None,
+                checksum: None,
+                size: None,
                 target_file: None,
+                max_bandwidth: None,
+                priority: 0,
+                idle_timeout: None,
+                deadline: None,
+                mirrors: Vec::new(),
             })
             .ok_or_else(|| {
                 Error::InvalidUrl {
@@ -224,7 +306,14 @@ impl TryFrom<&str> for Download {
             url,
             filename: decoded_filename,
             hash: None,
+            checksum: None,
+            size: None,
             target_file: None,
+            max_bandwidth: None,
+            priority: 0,
+            idle_timeout: None,
+            deadline: None,
+            mirrors: Vec::new(),
         })
     }
 }
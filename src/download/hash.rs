@@ -9,6 +9,10 @@
 //! - **MD5**: 32-character hexadecimal strings (e.g., "d41d8cd98f00b204e9800998ecf8427e")
 //! - **CRC32**: Numeric strings that can be parsed as u32 (e.g., "1127497")
 //!
+//! Guessing is only a legacy fallback, it misreads hex CRC32 values and cannot
+//! recognize stronger algorithms. Typed hashes such as `sha256:<hex>` are
+//! verified as [`Checksum`]s, see the [`checksum`](super::checksum) module.
+//!
 //! # Examples
 //!
 //! ## Hash Type Detection
@@ -44,6 +48,8 @@
 //! }
 //! ```
 
+use super::checksum::Checksum;
+
 use bacy::hash::{calculate_crc32, calculate_md5};
 use std::error::Error;
 use std::path::Path;
@@ -96,7 +102,9 @@ pub fn detect_hash_type(hash: &str) -> Option<HashType> {
 
 /// Verify hash of a local file against an expected hash.
 ///
-/// Returns true if hashes match or if no hash is provided.
+/// Returns true if hashes match or if no hash is provided. Hashes written as a
+/// typed [`Checksum`], e.g. `sha256:<hex>`, are verified with their algorithm,
+/// which is guessed otherwise.
 ///
 /// # Arguments
 ///
@@ -107,7 +115,8 @@ pub fn detect_hash_type(hash: &str) -> Option<HashType> {
 ///
 /// * `Ok(true)` if hashes match or no hash provided
 /// * `Ok(false)` if file doesn't exist or hashes don't match
-/// * `Err` if there's an error calculating the hash
+/// * `Err` if there's an error calculating the hash, or if its type cannot be
+///   recognized
 ///
 /// # Examples
 ///
@@ -136,6 +145,11 @@ pub fn verify_hash(
         return Ok(false);
     }
 
+    // Typed checksums need no guessing.
+    if let Ok(checksum) = expected_hash.parse::<Checksum>() {
+        return Ok(checksum.verify(file_path)?);
+    }
+
     let hash_type = detect_hash_type(expected_hash);
 
     match hash_type {
@@ -148,6 +162,6 @@ pub fn verify_hash(
             let expected_crc32: u32 = expected_hash.parse().map_err(|_| "Invalid CRC32 format")?;
             Ok(calculated_hash == expected_crc32)
         }
-        None => Ok(false),
+        None => Err("unrecognized hash format".into()),
     }
 }
@@ -0,0 +1,146 @@
+//! Checksums announced by servers in integrity headers.
+//!
+//! Many servers and object stores send a digest of the file along with it.
+//! [`response_checksum`] reads the following headers, and keeps the strongest
+//! digest they announce:
+//!
+//! | Header         | Specification      | Example                                  |
+//! |----------------|--------------------|------------------------------------------|
+//! | `Repr-Digest`  | RFC 9530           | `Repr-Digest: sha-256=:<base64>:`        |
+//! | `Digest`       | RFC 3230           | `Digest: SHA-256=<base64>`               |
+//! | `x-goog-hash`  | Google Cloud Storage | `x-goog-hash: crc32c=<base64>,md5=<base64>` |
+//! | `Content-MD5`  | RFC 1864           | `Content-MD5: <base64>`                  |
+//!
+//! Digests of unsupported algorithms, such as CRC32C, are ignored. So are the
+//! `Content-MD5` of partial responses, which only covers the range sent, and
+//! the `x-goog-hash` of objects transcoded by Google Cloud Storage, which
+//! covers the stored object rather than the one sent.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use reqwest::header::{HeaderMap, HeaderValue};
+//! use trauma::download::checksum::Algorithm;
+//! use trauma::download::integrity::{response_checksum, ChecksumSource};
+//!
+//! let mut headers = HeaderMap::new();
+//! headers.insert("content-md5", HeaderValue::from_static("CY9rzUYh03PK3k6DJie09g=="));
+//! headers.insert(
+//!     "repr-digest",
+//!     HeaderValue::from_static("sha-256=:n4bQgYhMfWWaL+qgxVrQFaO/TxsrC4Is0V1sFbDwCgg=:"),
+//! );
+//!
+//! let (source, checksum) = response_checksum(&headers).unwrap();
+//! assert_eq!(source, ChecksumSource::ReprDigest);
+//! assert_eq!(checksum.algorithm, Algorithm::Sha256);
+//! ```
+
+use super::checksum::{Algorithm, Checksum};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use reqwest::header::{HeaderMap, CONTENT_ENCODING, CONTENT_RANGE};
+use std::fmt;
+
+/// Where the checksum a file is verified against comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChecksumSource {
+    /// The [checksum](crate::download::Download::checksum) or
+    /// [hash](crate::download::Download::hash) of the download.
+    Download,
+    /// The RFC 9530 `Repr-Digest` header of the response.
+    ReprDigest,
+    /// The RFC 3230 `Digest` header of the response.
+    Digest,
+    /// The `x-goog-hash` header of a Google Cloud Storage response.
+    GoogHash,
+    /// The `Content-MD5` header of the response.
+    ContentMd5,
+}
+
+impl fmt::Display for ChecksumSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Download => "download",
+            Self::ReprDigest => "Repr-Digest",
+            Self::Digest => "Digest",
+            Self::GoogHash => "x-goog-hash",
+            Self::ContentMd5 => "Content-MD5",
+        })
+    }
+}
+
+/// Get the strongest checksum announced by the integrity headers of a
+/// response, along with the header it comes from.
+///
+/// Among equally strong checksums, `Repr-Digest` is preferred, then `Digest`,
+/// `x-goog-hash` and `Content-MD5`.
+pub fn response_checksum(headers: &HeaderMap) -> Option<(ChecksumSource, Checksum)> {
+    let mut checksums = Vec::new();
+    for value in header_values(headers, "repr-digest") {
+        // Dictionary members look like `sha-256=:<base64>:;param`.
+        let digests = value.split(',').filter_map(|member| {
+            let (name, digest) = member.split_once('=')?;
+            let digest = digest.split(';').next()?.trim();
+            Some((name, digest.strip_prefix(':')?.strip_suffix(':')?))
+        });
+        checksums.extend(decode_all(ChecksumSource::ReprDigest, digests));
+    }
+    for value in header_values(headers, "digest") {
+        let digests = value.split(',').filter_map(|member| member.split_once('='));
+        checksums.extend(decode_all(ChecksumSource::Digest, digests));
+    }
+    if !is_transcoded(headers) {
+        for value in header_values(headers, "x-goog-hash") {
+            let digests = value.split(',').filter_map(|member| member.split_once('='));
+            checksums.extend(decode_all(ChecksumSource::GoogHash, digests));
+        }
+    }
+    if !headers.contains_key(CONTENT_RANGE) {
+        let digests = header_values(headers, "content-md5").map(|digest| ("md5", digest));
+        checksums.extend(decode_all(ChecksumSource::ContentMd5, digests));
+    }
+
+    checksums.into_iter().reduce(|best, (source, checksum)| {
+        match checksum.algorithm.strength() > best.1.algorithm.strength() {
+            true => (source, checksum),
+            false => best,
+        }
+    })
+}
+
+/// Get the values of the header `name` that are valid strings.
+fn header_values<'a>(headers: &'a HeaderMap, name: &str) -> impl Iterator<Item = &'a str> {
+    headers
+        .get_all(name)
+        .into_iter()
+        .filter_map(|value| value.to_str().ok())
+}
+
+/// Decode the base64 `digests` of supported algorithms, by algorithm name.
+fn decode_all<'a>(
+    source: ChecksumSource,
+    digests: impl Iterator<Item = (&'a str, &'a str)> + 'a,
+) -> impl Iterator<Item = (ChecksumSource, Checksum)> + 'a {
+    digests.filter_map(move |(name, digest)| {
+        let algorithm = match name.trim().to_ascii_lowercase().as_str() {
+            // RFC 3230 names SHA-1 `SHA`.
+            "sha" => Algorithm::Sha1,
+            name => name.parse().ok()?,
+        };
+        let value = STANDARD.decode(digest.trim()).ok()?;
+        (value.len() == algorithm.digest_len()).then_some((source, Checksum { algorithm, value }))
+    })
+}
+
+/// Check whether Google Cloud Storage decompressed the object it sends, in
+/// which case its `x-goog-hash` does not match the data received.
+fn is_transcoded(headers: &HeaderMap) -> bool {
+    let encoding = |name| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| !value.eq_ignore_ascii_case("identity"))
+    };
+    encoding("x-goog-stored-content-encoding") != encoding(CONTENT_ENCODING.as_str())
+}
@@ -0,0 +1,279 @@
+//! Persistent resume journal for interrupted downloads.
+//!
+//! When journaling is enabled on the [`Downloader`](crate::downloader::Downloader),
+//! a small JSON sidecar named after the destination file with a `.trauma.json`
+//! suffix (e.g. `file.zip.trauma.json`) is written next to the temporary
+//! `.part` file. It records everything needed to safely resume the download
+//! from another process, and is removed once the download succeeds.
+//!
+//! The journal is also where the validators used for
+//! [resume validation](crate::download::resume) are kept, so it is written
+//! for any download whose server sent them, even with journaling disabled.
+//!
+//! # Format
+//!
+//! The journal is a JSON object with the following fields. New optional fields
+//! may be added without changing the `version`, any incompatible change will
+//! bump it.
+//!
+//! | Field             | Type                | Description                                        |
+//! |-------------------|---------------------|----------------------------------------------------|
+//! | `version`         | integer             | Format version, currently `1`                      |
+//! | `url`             | string              | URL the data was downloaded from                   |
+//! | `etag`            | string or `null`    | `ETag` of the remote file, as sent by the server   |
+//! | `last_modified`   | string or `null`    | `Last-Modified` date of the remote file            |
+//! | `total_length`    | integer or `null`   | Size of the complete file in bytes, if known       |
+//! | `bytes_confirmed` | integer             | Number of bytes known to be written to the `.part` |
+//! | `segments`        | array               | State of each segment of a segmented download      |
+//!
+//! Each segment is an object with the inclusive `start` and `end` offsets of
+//! its byte range and the number of bytes already `written` from `start`. The
+//! array is empty for downloads fetched over a single connection, in which
+//! case the first `bytes_confirmed` bytes of the `.part` file are valid.
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "url": "https://example.com/file.zip",
+//!   "etag": "\"33a64df5\"",
+//!   "last_modified": null,
+//!   "total_length": 4194304,
+//!   "bytes_confirmed": 1572864,
+//!   "segments": [
+//!     { "start": 0, "end": 2097151, "written": 1048576 },
+//!     { "start": 2097152, "end": 4194303, "written": 524288 }
+//!   ]
+//! }
+//! ```
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use std::path::Path;
+//! use trauma::download::journal::{journal_path, Journal};
+//!
+//! # async fn example() {
+//! let path = journal_path(Path::new("output/file.zip"));
+//! if let Some(journal) = Journal::load(&path).await {
+//!     println!(
+//!         "{}: {} of {:?} bytes downloaded",
+//!         journal.url, journal.bytes_confirmed, journal.total_length
+//!     );
+//! }
+//! # }
+//! ```
+
+use crate::downloader::segment::Segment;
+use crate::utils::path::append_extension;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ETAG, LAST_MODIFIED};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// Suffix appended to the destination file name to name its journal.
+pub const JOURNAL_SUFFIX: &str = "trauma.json";
+
+/// Current version of the journal format.
+pub const JOURNAL_VERSION: u32 = 1;
+
+/// Number of bytes downloaded between two journal updates (1 MiB).
+pub const CHECKPOINT_INTERVAL: u64 = 1024 * 1024;
+
+/// Progress of a single segment of a segmented download.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SegmentState {
+    /// Offset of the first byte of the segment.
+    pub start: u64,
+    /// Offset of the last byte of the segment.
+    pub end: u64,
+    /// Number of bytes written from the start of the segment.
+    pub written: u64,
+}
+
+impl SegmentState {
+    /// Byte range covered by the segment.
+    pub fn segment(&self) -> Segment {
+        Segment {
+            start: self.start,
+            end: self.end,
+        }
+    }
+
+    /// Return `true` once the whole segment was written.
+    pub fn is_complete(&self) -> bool {
+        self.written >= self.segment().len()
+    }
+}
+
+impl From<Segment> for SegmentState {
+    fn from(segment: Segment) -> Self {
+        Self {
+            start: segment.start,
+            end: segment.end,
+            written: 0,
+        }
+    }
+}
+
+/// Resume state of a download, persisted next to its `.part` file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Journal {
+    /// Format version.
+    pub version: u32,
+    /// URL the data was downloaded from.
+    pub url: String,
+    /// `ETag` of the remote file.
+    pub etag: Option<String>,
+    /// `Last-Modified` date of the remote file.
+    pub last_modified: Option<String>,
+    /// Size of the complete file in bytes.
+    pub total_length: Option<u64>,
+    /// Number of bytes known to be written to the `.part` file.
+    pub bytes_confirmed: u64,
+    /// State of each segment of a segmented download.
+    #[serde(default)]
+    pub segments: Vec<SegmentState>,
+}
+
+impl Journal {
+    /// Create a journal for a new download of `url`.
+    ///
+    /// The validators are read from the `headers` of the first response.
+    pub fn new(url: &Url, headers: &HeaderMap, total_length: Option<u64>) -> Self {
+        Self {
+            version: JOURNAL_VERSION,
+            url: url.to_string(),
+            etag: header_string(headers, ETAG),
+            last_modified: header_string(headers, LAST_MODIFIED),
+            total_length,
+            bytes_confirmed: 0,
+            segments: Vec::new(),
+        }
+    }
+
+    /// Create a journal for a new segmented download of `url`.
+    ///
+    /// The validators are read from the `headers` describing the remote file
+    /// before any segment is requested, so that every segment can be made
+    /// conditional on them.
+    pub fn segmented(
+        url: &Url,
+        headers: &HeaderMap,
+        total_length: u64,
+        segments: &[Segment],
+    ) -> Self {
+        Self {
+            total_length: Some(total_length),
+            segments: segments.iter().copied().map(SegmentState::from).collect(),
+            ..Self::new(url, headers, Some(total_length))
+        }
+    }
+
+    /// Return `true` if the journal describes a segmented download.
+    pub fn is_segmented(&self) -> bool {
+        !self.segments.is_empty()
+    }
+
+    /// Return `true` if the journal can be used to resume a download of `url`.
+    pub fn matches(&self, url: &Url) -> bool {
+        self.version == JOURNAL_VERSION && self.url == url.as_str()
+    }
+
+    /// Value to send in an `If-Range` header when resuming.
+    ///
+    /// Weak ETags cannot be used for range requests, the modification date is
+    /// used instead.
+    pub fn validator(&self) -> Option<HeaderValue> {
+        self.etag
+            .as_deref()
+            .filter(|etag| !etag.starts_with("W/"))
+            .or(self.last_modified.as_deref())
+            .and_then(|v| HeaderValue::from_str(v).ok())
+    }
+
+    /// Record the validators of the remote file if none are known yet.
+    pub fn set_validators(&mut self, headers: &HeaderMap) {
+        if self.etag.is_none() && self.last_modified.is_none() {
+            self.etag = header_string(headers, ETAG);
+            self.last_modified = header_string(headers, LAST_MODIFIED);
+        }
+    }
+
+    /// Return `false` if `headers` describe another version of the remote file
+    /// than the validators recorded so far.
+    pub fn same_version(&self, headers: &HeaderMap) -> bool {
+        let differs = |known: &Option<String>, name| match (known, header_string(headers, name)) {
+            (Some(known), Some(received)) => *known != received,
+            _ => false,
+        };
+        !differs(&self.etag, ETAG) && !differs(&self.last_modified, LAST_MODIFIED)
+    }
+
+    /// Update the progress of the segment at `index`.
+    pub fn set_segment_written(&mut self, index: usize, written: u64) {
+        if let Some(state) = self.segments.get_mut(index) {
+            state.written = written;
+        }
+        self.bytes_confirmed = self.segments.iter().map(|s| s.written).sum();
+    }
+
+    /// Load the journal stored at `path`.
+    ///
+    /// Returns `None` if the journal does not exist or cannot be parsed.
+    pub async fn load(path: &Path) -> Option<Self> {
+        let content = fs::read(path).await.ok()?;
+        serde_json::from_slice(&content).ok()
+    }
+
+    /// Store the journal at `path`.
+    ///
+    /// The journal is written to a temporary file first, then renamed, so that
+    /// an interruption never leaves a truncated journal behind.
+    pub async fn save(&self, path: &Path) -> io::Result<()> {
+        let content = serde_json::to_vec_pretty(self).map_err(io::Error::other)?;
+        let tmp = temporary_path(path);
+        fs::write(&tmp, content).await?;
+        fs::rename(&tmp, path).await
+    }
+
+    /// Remove the journal stored at `path`.
+    ///
+    /// A missing journal is not an error.
+    pub async fn remove(path: &Path) {
+        let _ = fs::remove_file(path).await;
+    }
+}
+
+/// Read a header as a string, ignoring values that are not valid ASCII.
+fn header_string(headers: &HeaderMap, name: HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(String::from)
+}
+
+/// Path of the journal of the download written to `output`.
+///
+/// # Example
+///
+/// ```rust
+/// use std::path::{Path, PathBuf};
+/// use trauma::download::journal::journal_path;
+///
+/// assert_eq!(
+///     journal_path(Path::new("output/file.zip")),
+///     PathBuf::from("output/file.zip.trauma.json")
+/// );
+/// ```
+pub fn journal_path(output: &Path) -> PathBuf {
+    append_extension(output, JOURNAL_SUFFIX)
+}
+
+/// Path of the temporary file the journal at `path` is written to before
+/// being renamed.
+pub(crate) fn temporary_path(path: &Path) -> PathBuf {
+    append_extension(path, "tmp")
+}
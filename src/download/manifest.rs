@@ -0,0 +1,267 @@
+//! Checksum manifests such as `SHA256SUMS` or `MD5SUMS`.
+//!
+//! A [`Manifest`] maps file names to their [`Checksum`], and can attach them
+//! to a batch of downloads by file name with [`Manifest::apply`]. The
+//! following formats are understood, and may be mixed:
+//!
+//! - GNU coreutils, as written by `sha256sum` or `md5sum`:
+//!   `<hex digest>  <file>`, or `<hex digest> *<file>` in binary mode.
+//! - BSD, as written by `sha256` or `sha256sum --tag`:
+//!   `SHA256 (<file>) = <hex digest>`.
+//!
+//! GNU lines do not name their algorithm. It is taken from the name of the
+//! manifest when possible (e.g. `SHA256SUMS`, `B3SUMS` or `checksums.sha512`),
+//! and guessed from the length of the digest otherwise, assuming MD5 or a SHA
+//! algorithm. Empty lines and lines starting with `#` are ignored.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use trauma::download::manifest::Manifest;
+//! use trauma::download::Download;
+//! use std::convert::TryFrom;
+//!
+//! let manifest = Manifest::parse(
+//!     "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08  test.txt\n",
+//!     None,
+//! )?;
+//!
+//! let mut downloads = vec![
+//!     Download::try_from("https://example.com/test.txt")?,
+//!     Download::try_from("https://example.com/other.txt")?,
+//! ];
+//! let missing = manifest.apply(&mut downloads);
+//! assert!(downloads[0].checksum.is_some());
+//! assert_eq!(missing, vec![1]);
+//! # Ok::<(), trauma::Error>(())
+//! ```
+
+use super::checksum::{Algorithm, Checksum};
+use super::download::Download;
+use crate::error::Error;
+
+use reqwest::Url;
+use reqwest_middleware::ClientWithMiddleware;
+use std::path::Path;
+
+/// Checksums of files, by file name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Manifest {
+    /// File names and their checksum, in the order of the manifest.
+    entries: Vec<(String, Checksum)>,
+}
+
+impl Manifest {
+    /// Parse the content of a manifest.
+    ///
+    /// `algorithm` is the algorithm of the GNU lines, which is guessed from the
+    /// length of their digest when not provided.
+    pub fn parse(text: &str, algorithm: Option<Algorithm>) -> Result<Self, Error> {
+        let mut entries = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim_end_matches('\r');
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let entry = match parse_bsd(line) {
+                Some(entry) => entry,
+                None => parse_gnu(line, algorithm),
+            };
+            let (name, checksum) = entry.map_err(|message| Error::Manifest {
+                message: format!("line {}: {}", number + 1, message).into(),
+            })?;
+            entries.push((normalize(&name).to_string(), checksum));
+        }
+
+        Ok(Self { entries })
+    }
+
+    /// Read a manifest from a local file.
+    ///
+    /// The algorithm of the GNU lines is taken from the name of the file if
+    /// possible.
+    pub async fn from_file(path: &Path) -> Result<Self, Error> {
+        let text = tokio::fs::read_to_string(path).await?;
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        Self::parse(&text, algorithm_of(&name))
+    }
+
+    /// Download a manifest with `client`.
+    ///
+    /// The algorithm of the GNU lines is taken from the last segment of the
+    /// URL if possible.
+    pub async fn fetch(client: &ClientWithMiddleware, url: &Url) -> Result<Self, Error> {
+        let text = client
+            .get(url.clone())
+            .send()
+            .await
+            .map_err(Error::from_middleware)?
+            .error_for_status()?
+            .text()
+            .await
+            .map_err(Error::from_reqwest)?;
+        let name = url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .unwrap_or_default();
+        Self::parse(&text, algorithm_of(name))
+    }
+
+    /// Get the checksum of the file `name`.
+    ///
+    /// A file listed with directories (e.g. `dist/app.tar.gz`) is also found
+    /// by its base name, unless several listed files share it.
+    pub fn get(&self, name: &str) -> Option<&Checksum> {
+        let name = normalize(name);
+        if let Some((_, checksum)) = self.entries.iter().find(|(n, _)| n == name) {
+            return Some(checksum);
+        }
+
+        let base = base_name(name);
+        let mut matches = self.entries.iter().filter(|(n, _)| base_name(n) == base);
+        match (matches.next(), matches.next()) {
+            (Some((_, checksum)), None) => Some(checksum),
+            _ => None,
+        }
+    }
+
+    /// Get the number of files in the manifest.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check whether the manifest lists no files.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterate over the file names and checksums, in the order of the manifest.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Checksum)> {
+        self.entries
+            .iter()
+            .map(|(name, checksum)| (name.as_str(), checksum))
+    }
+
+    /// Set the checksum of each download to the one of its file name.
+    ///
+    /// Returns the indices of the downloads missing from the manifest, whose
+    /// checksum is left untouched.
+    pub fn apply(&self, downloads: &mut [Download]) -> Vec<usize> {
+        let mut missing = Vec::new();
+        for (index, download) in downloads.iter_mut().enumerate() {
+            match self.get(&download.filename) {
+                Some(checksum) => download.checksum = Some(checksum.clone()),
+                None => missing.push(index),
+            }
+        }
+        missing
+    }
+}
+
+/// Parse a BSD line, `<ALGORITHM> (<file>) = <digest>`.
+///
+/// Returns `None` if the line is not in the BSD format.
+fn parse_bsd(line: &str) -> Option<Result<(String, Checksum), String>> {
+    let (tag, rest) = line.split_once(" (")?;
+    let (name, digest) = rest.rsplit_once(") = ")?;
+    if tag.is_empty() || !tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return None;
+    }
+
+    let checksum = tag
+        .parse()
+        .and_then(|algorithm| Checksum::from_hex(algorithm, digest))
+        .map_err(|e| describe(&e));
+    Some(checksum.map(|checksum| (name.to_string(), checksum)))
+}
+
+/// Parse a GNU line, `<digest>  <file>` or `<digest> *<file>`.
+fn parse_gnu(line: &str, algorithm: Option<Algorithm>) -> Result<(String, Checksum), String> {
+    // Names with a backslash or a newline are escaped, and flagged with a
+    // leading backslash.
+    let (escaped, line) = match line.strip_prefix('\\') {
+        Some(line) => (true, line),
+        None => (false, line),
+    };
+    let (digest, rest) = line
+        .split_once(' ')
+        .ok_or("expected `<digest>  <file>` or `<ALGORITHM> (<file>) = <digest>`")?;
+    let name = rest
+        .strip_prefix(' ')
+        .or_else(|| rest.strip_prefix('*'))
+        .filter(|name| !name.is_empty())
+        .ok_or("expected two spaces or ` *` before the file name")?;
+    let name = match escaped {
+        true => unescape(name),
+        false => name.to_string(),
+    };
+
+    let algorithm = match algorithm {
+        Some(algorithm) => algorithm,
+        None => match digest.len() {
+            32 => Algorithm::Md5,
+            40 => Algorithm::Sha1,
+            64 => Algorithm::Sha256,
+            128 => Algorithm::Sha512,
+            _ => return Err(format!("cannot guess the algorithm of {:?}", digest)),
+        },
+    };
+    let checksum = Checksum::from_hex(algorithm, digest).map_err(|e| describe(&e))?;
+    Ok((name, checksum))
+}
+
+/// Undo the escaping of `\\` and `\n` in a GNU file name.
+fn unescape(name: &str) -> String {
+    let mut unescaped = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('\\')) => {
+                chars.next();
+                unescaped.push('\\');
+            }
+            ('\\', Some('n')) => {
+                chars.next();
+                unescaped.push('\n');
+            }
+            (c, _) => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+/// Guess the algorithm of a manifest from its name.
+///
+/// Handles names such as `SHA256SUMS`, `md5sums.txt`, `B3SUMS` or
+/// `checksums.sha512`.
+fn algorithm_of(name: &str) -> Option<Algorithm> {
+    let name = name.to_ascii_lowercase();
+    let prefix = name.find("sum").map(|i| name[..i].to_string());
+    let extension = name.rsplit_once('.').map(|(_, e)| e.to_string());
+    [prefix, extension]
+        .into_iter()
+        .flatten()
+        .find_map(|candidate| match candidate.as_str() {
+            "b3" => Some(Algorithm::Blake3),
+            candidate => candidate.parse().ok(),
+        })
+}
+
+/// Drop the leading `./` of a file name.
+fn normalize(name: &str) -> &str {
+    name.trim_start_matches("./")
+}
+
+/// Get the last component of a file name.
+fn base_name(name: &str) -> &str {
+    name.rsplit('/').next().unwrap_or(name)
+}
+
+/// Describe a checksum error along with its cause.
+fn describe(e: &Error) -> String {
+    match std::error::Error::source(e) {
+        Some(cause) => format!("{}: {}", e, cause),
+        None => e.to_string(),
+    }
+}
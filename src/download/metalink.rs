@@ -0,0 +1,250 @@
+//! Metalink (RFC 5854) input.
+//!
+//! A Metalink document lists files along with the URLs serving them, their
+//! size and their hashes. [`parse_metalink`] turns each `<file>` element of a
+//! `.meta4` document into a [`Download`]:
+//!
+//! | Metalink                 | Download                                           |
+//! |--------------------------|----------------------------------------------------|
+//! | `name` attribute         | [`filename`](Download::filename)                   |
+//! | `<url>` elements         | [`url`](Download::url) and [`mirrors`](Download::mirrors), by priority |
+//! | `<size>`                 | [`size`](Download::size)                           |
+//! | `<hash>` elements        | [`checksum`](Download::checksum), the strongest one |
+//!
+//! Only HTTP(S) URLs are kept, and URLs without a `priority` come after the
+//! ones with one. Hashes of unsupported types, piece hashes, `<metaurl>`
+//! elements and descriptive metadata are ignored.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use trauma::download::checksum::Algorithm;
+//! use trauma::download::metalink::parse_metalink;
+//!
+//! let document = r#"<?xml version="1.0" encoding="UTF-8"?>
+//! <metalink xmlns="urn:ietf:params:xml:ns:metalink">
+//!   <file name="example.ext">
+//!     <size>14471447</size>
+//!     <hash type="md5">d41d8cd98f00b204e9800998ecf8427e</hash>
+//!     <hash type="sha-256">e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855</hash>
+//!     <url priority="2">https://mirror.example.org/example.ext</url>
+//!     <url priority="1">https://example.com/example.ext</url>
+//!   </file>
+//! </metalink>"#;
+//!
+//! let downloads = parse_metalink(document)?;
+//! assert_eq!(downloads[0].filename, "example.ext");
+//! assert_eq!(downloads[0].url.as_str(), "https://example.com/example.ext");
+//! assert_eq!(downloads[0].mirrors[0].as_str(), "https://mirror.example.org/example.ext");
+//! assert_eq!(downloads[0].size, Some(14471447));
+//! assert_eq!(downloads[0].checksum.as_ref().unwrap().algorithm, Algorithm::Sha256);
+//! # Ok::<(), trauma::Error>(())
+//! ```
+
+use super::checksum::{Algorithm, Checksum};
+use super::download::Download;
+use crate::error::Error;
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use reqwest::Url;
+use std::path::{Component, Path};
+
+/// Parse a Metalink document into the downloads it describes.
+///
+/// Fails if the document is not a Metalink document, or if one of its files
+/// has an unsafe name, an invalid URL or no HTTP(S) URL at all.
+pub fn parse_metalink(document: &str) -> Result<Vec<Download>, Error> {
+    let mut reader = Reader::from_str(document);
+    let mut downloads = Vec::new();
+    let mut is_metalink = false;
+    let mut file: Option<FileEntry> = None;
+    let mut field: Option<Field> = None;
+    let mut in_pieces = false;
+    let mut text = String::new();
+
+    loop {
+        match reader.read_event().map_err(invalid)? {
+            Event::Start(e) => {
+                match (e.local_name().as_ref(), file.is_some()) {
+                    (b"metalink", false) => is_metalink = true,
+                    (b"file", false) => file = Some(FileEntry::new(&e)?),
+                    (b"pieces", true) => in_pieces = true,
+                    (b"size", true) => field = Some(Field::Size),
+                    (b"hash", true) if !in_pieces => {
+                        field = Some(Field::Hash(attribute(&e, "type")?.unwrap_or_default()))
+                    }
+                    (b"url", true) => field = Some(Field::Url(priority(&e)?)),
+                    _ => (),
+                }
+                text.clear();
+            }
+            Event::Empty(e) => match e.local_name().as_ref() {
+                b"metalink" => is_metalink = true,
+                b"file" => downloads.push(FileEntry::new(&e)?.into_download()?),
+                _ => (),
+            },
+            Event::Text(e) => text.push_str(&e.unescape().map_err(invalid)?),
+            Event::CData(e) => text.push_str(&String::from_utf8_lossy(&e)),
+            Event::End(e) => match e.local_name().as_ref() {
+                b"file" => {
+                    if let Some(file) = file.take() {
+                        downloads.push(file.into_download()?);
+                    }
+                }
+                b"pieces" => in_pieces = false,
+                _ => {
+                    if let (Some(field), Some(file)) = (field.take(), file.as_mut()) {
+                        file.set(field, text.trim())?;
+                    }
+                }
+            },
+            Event::Eof if file.is_some() => return Err(error("unexpected end of document")),
+            Event::Eof => break,
+            _ => (),
+        }
+    }
+
+    match is_metalink {
+        true => Ok(downloads),
+        false => Err(error("the root element is not <metalink>")),
+    }
+}
+
+/// Element of a file whose text is being read.
+#[derive(Debug)]
+enum Field {
+    /// Size of the file.
+    Size,
+    /// Hash of the file, with its type.
+    Hash(String),
+    /// URL of the file, with its priority.
+    Url(u32),
+}
+
+/// A `<file>` element being read.
+#[derive(Debug)]
+struct FileEntry {
+    /// Name of the file.
+    name: String,
+    /// Size of the file.
+    size: Option<u64>,
+    /// Strongest checksum of the file.
+    checksum: Option<Checksum>,
+    /// HTTP(S) URLs of the file, with their priority.
+    urls: Vec<(u32, Url)>,
+}
+
+impl FileEntry {
+    /// Start reading the `<file>` element `e`.
+    fn new(e: &BytesStart) -> Result<Self, Error> {
+        let name = attribute(e, "name")?.ok_or_else(|| error("a file has no name"))?;
+
+        // The name may contain directories, but must stay in the download
+        // directory.
+        let safe = Path::new(&name)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)));
+        if name.is_empty() || !safe {
+            return Err(error(format!("unsafe file name {:?}", name)));
+        }
+
+        Ok(Self {
+            name,
+            size: None,
+            checksum: None,
+            urls: Vec::new(),
+        })
+    }
+
+    /// Store the `text` of `field`.
+    fn set(&mut self, field: Field, text: &str) -> Result<(), Error> {
+        match field {
+            Field::Size => {
+                let size = text.parse().map_err(|e| Error::Metalink {
+                    message: format!("invalid size of {:?}", self.name).into(),
+                    cause: Some(Box::new(e)),
+                })?;
+                self.size = Some(size);
+            }
+            Field::Hash(kind) => {
+                // Hashes of unsupported types are ignored.
+                let Ok(algorithm) = kind.parse::<Algorithm>() else {
+                    return Ok(());
+                };
+                let checksum = Checksum::from_hex(algorithm, text)?;
+                let stronger = self
+                    .checksum
+                    .as_ref()
+                    .is_none_or(|c| c.algorithm.strength() < algorithm.strength());
+                if stronger {
+                    self.checksum = Some(checksum);
+                }
+            }
+            Field::Url(priority) => {
+                let url = Url::parse(text).map_err(|e| Error::InvalidUrl {
+                    url: text.into(),
+                    cause: Box::new(e),
+                })?;
+                if matches!(url.scheme(), "http" | "https") {
+                    self.urls.push((priority, url));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Turn the file into a download from its preferred URL.
+    fn into_download(mut self) -> Result<Download, Error> {
+        // The sort is stable, URLs of equal priority keep the document order.
+        self.urls.sort_by_key(|(priority, _)| *priority);
+        let mut urls = self.urls.into_iter().map(|(_, url)| url);
+        let url = urls
+            .next()
+            .ok_or_else(|| error(format!("{:?} has no HTTP(S) URL", self.name)))?;
+
+        Ok(Download {
+            size: self.size,
+            checksum: self.checksum,
+            mirrors: urls.collect(),
+            ..Download::new(url, &self.name)
+        })
+    }
+}
+
+/// Get the value of the attribute `name` of `e`.
+fn attribute(e: &BytesStart, name: &str) -> Result<Option<String>, Error> {
+    match e.try_get_attribute(name).map_err(invalid)? {
+        Some(attribute) => Ok(Some(
+            attribute.unescape_value().map_err(invalid)?.into_owned(),
+        )),
+        None => Ok(None),
+    }
+}
+
+/// Get the priority of the `<url>` element `e`, the lowest goes first.
+fn priority(e: &BytesStart) -> Result<u32, Error> {
+    match attribute(e, "priority")? {
+        Some(priority) => priority
+            .trim()
+            .parse()
+            .map_err(|_| error(format!("invalid URL priority {:?}", priority))),
+        None => Ok(u32::MAX),
+    }
+}
+
+/// Create an error about the content of the document.
+fn error(message: impl Into<Box<str>>) -> Error {
+    Error::Metalink {
+        message: message.into(),
+        cause: None,
+    }
+}
+
+/// Convert an XML parsing error.
+fn invalid(e: impl std::error::Error + Send + Sync + 'static) -> Error {
+    Error::Metalink {
+        message: e.to_string().into(),
+        cause: Some(Box::new(e)),
+    }
+}
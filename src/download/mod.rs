@@ -6,11 +6,17 @@
 //!
 //! # Overview
 //!
-//! The download module is organized into three main components:
+//! The download module is organized into the following components:
 //!
 //! - [`download`] - Core Download struct and URL handling
 //! - [`summary`] - Download result tracking and status reporting  
+//! - [`checksum`] - Explicitly typed checksums
 //! - [`hash`] - File integrity verification through hash checking
+//! - [`integrity`] - Checksums announced by servers in integrity headers
+//! - [`resume`] - Validation of partial downloads before resuming them
+//! - [`journal`] - Persistent resume state of interrupted downloads
+//! - [`manifest`] - Checksum manifests such as `SHA256SUMS`
+//! - [`metalink`] - Downloads described by Metalink documents
 //!
 //! # Examples
 //!
@@ -66,10 +72,20 @@
 //! # }
 //! ```
 
+pub mod checksum;
 pub mod download;
 pub mod hash;
+pub mod integrity;
+pub mod journal;
+pub mod manifest;
+pub mod metalink;
+pub mod resume;
 pub mod summary;
 
+pub use checksum::{Algorithm, Checksum};
 pub use download::Download;
 pub use hash::{detect_hash_type, verify_hash, HashType};
-pub use summary::{Status, Summary};
+pub use integrity::ChecksumSource;
+pub use manifest::Manifest;
+pub use metalink::parse_metalink;
+pub use summary::{ResumeOutcome, Status, Summary};
@@ -0,0 +1,38 @@
+//! Resume validation for partially downloaded files.
+//!
+//! When a download starts, the validators of the representation being fetched
+//! (its `ETag` and `Last-Modified` date) are stored in the
+//! [`journal`](crate::download::journal) next to the temporary `.part` file.
+//! Resuming sends one back in an `If-Range` header so that the server only
+//! honors the range request if the remote file did not change in the meantime.
+//! Otherwise the server answers with the full file and the partial data is
+//! discarded.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use reqwest::header::{HeaderMap, HeaderValue, ETAG, LAST_MODIFIED};
+//! use trauma::download::resume::if_range_value;
+//!
+//! let mut headers = HeaderMap::new();
+//! headers.insert(LAST_MODIFIED, HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"));
+//! assert_eq!(if_range_value(&headers).unwrap(), "Wed, 21 Oct 2015 07:28:00 GMT");
+//!
+//! // A strong ETag takes precedence over the modification date.
+//! headers.insert(ETAG, HeaderValue::from_static("\"33a64df5\""));
+//! assert_eq!(if_range_value(&headers).unwrap(), "\"33a64df5\"");
+//! ```
+
+use reqwest::header::{HeaderMap, HeaderValue, ETAG, LAST_MODIFIED};
+
+/// Pick the value to send in an `If-Range` header from response headers.
+///
+/// Weak ETags cannot be used for range requests, so the `Last-Modified` date
+/// is used instead when the ETag is weak or missing.
+pub fn if_range_value(headers: &HeaderMap) -> Option<HeaderValue> {
+    let strong_etag = headers
+        .get(ETAG)
+        .filter(|etag| !etag.as_bytes().starts_with(b"W/"));
+
+    strong_etag.or_else(|| headers.get(LAST_MODIFIED)).cloned()
+}
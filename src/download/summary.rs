@@ -24,6 +24,10 @@
 //!     Status::Fail(msg) => println!("Download failed: {}", msg),
 //!     Status::Skipped(reason) => println!("Download skipped: {}", reason),
 //!     Status::HashMismatch(details) => println!("Hash mismatch: {}", details),
+//!     Status::Cancelled => println!("Download cancelled"),
+//!     Status::TimedOut(kind) => println!("Download timed out: {}", kind),
+//!     Status::Verified => println!("File matches its hash"),
+//!     Status::Missing => println!("File is missing"),
 //! }
 //!
 //! // Mark as failed
@@ -53,8 +57,13 @@
 //! # }
 //! ```
 
+use super::checksum::Checksum;
 use super::download::Download;
-use reqwest::StatusCode;
+use super::integrity::ChecksumSource;
+use crate::error::TimeoutKind;
+
+use reqwest::{StatusCode, Url};
+use std::path::{Path, PathBuf};
 
 /// Download status enumeration.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -69,6 +78,26 @@ pub enum Status {
     Success,
     /// Download completed but hash verification failed
     HashMismatch(String),
+    /// Download was cancelled before completion
+    Cancelled,
+    /// Download failed because a timeout expired
+    TimedOut(TimeoutKind),
+    /// Audited file exists and matches its hash
+    Verified,
+    /// Audited file does not exist
+    Missing,
+}
+
+/// Outcome of an attempt to resume a partial download.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResumeOutcome {
+    /// There was no partial download to resume
+    #[default]
+    NotAttempted,
+    /// The server honored the range request and the download was resumed
+    Honored,
+    /// The remote file changed or the range was ignored, the download restarted from scratch
+    Restarted,
 }
 
 /// Represents a [`Download`] summary.
@@ -84,6 +113,18 @@ pub struct Summary {
     status: Status,
     /// Resumable.
     resumable: bool,
+    /// Outcome of the resume attempt.
+    resume_outcome: ResumeOutcome,
+    /// Number of times the transfer was resumed after losing its connection.
+    recoveries: u32,
+    /// URL the file was fetched from, among the URL and mirrors of the download.
+    served_by: Option<Url>,
+    /// Checksum of the file, computed while it was downloaded.
+    checksum: Option<Checksum>,
+    /// Checksum the file is verified against, and where it comes from.
+    expected_checksum: Option<(ChecksumSource, Checksum)>,
+    /// Where a file that did not match its hash was moved to.
+    quarantined: Option<PathBuf>,
 }
 
 impl Summary {
@@ -95,6 +136,12 @@ impl Summary {
             size,
             status: Status::NotStarted,
             resumable,
+            resume_outcome: ResumeOutcome::NotAttempted,
+            recoveries: 0,
+            served_by: None,
+            checksum: None,
+            expected_checksum: None,
+            quarantined: None,
         }
     }
 
@@ -118,6 +165,15 @@ impl Summary {
         &self.download
     }
 
+    /// Get the name the file was saved under.
+    ///
+    /// It differs from the filename the download was created with when the
+    /// downloader derives filenames from the responses, see
+    /// [`DownloaderBuilder::filename_from_response`](crate::downloader::DownloaderBuilder::filename_from_response).
+    pub fn filename(&self) -> &str {
+        &self.download.filename
+    }
+
     /// Get a reference to the summary's status.
     pub fn status(&self) -> &Status {
         &self.status
@@ -147,6 +203,38 @@ impl Summary {
         }
     }
 
+    /// Mark the summary as verified.
+    pub fn verified(self) -> Self {
+        Self {
+            status: Status::Verified,
+            ..self
+        }
+    }
+
+    /// Mark the summary as missing.
+    pub fn missing(self) -> Self {
+        Self {
+            status: Status::Missing,
+            ..self
+        }
+    }
+
+    /// Mark the summary as cancelled.
+    pub fn cancel(self) -> Self {
+        Self {
+            status: Status::Cancelled,
+            ..self
+        }
+    }
+
+    /// Mark the summary as failed because the `kind` timeout expired.
+    pub fn time_out(self, kind: TimeoutKind) -> Self {
+        Self {
+            status: Status::TimedOut(kind),
+            ..self
+        }
+    }
+
     /// Set the summary's resumable.
     pub fn set_resumable(&mut self, resumable: bool) {
         self.resumable = resumable;
@@ -157,4 +245,106 @@ impl Summary {
     pub fn resumable(&self) -> bool {
         self.resumable
     }
+
+    /// Attach the outcome of the resume attempt to the summary.
+    pub fn with_resume_outcome(self, resume_outcome: ResumeOutcome) -> Self {
+        Self {
+            resume_outcome,
+            ..self
+        }
+    }
+
+    /// Get whether a partial download was resumed or restarted.
+    pub fn resume_outcome(&self) -> ResumeOutcome {
+        self.resume_outcome
+    }
+
+    /// Attach the number of mid-stream recoveries to the summary.
+    pub fn with_recoveries(self, recoveries: u32) -> Self {
+        Self { recoveries, ..self }
+    }
+
+    /// Get how many times the transfer was resumed after losing its connection.
+    pub fn recoveries(&self) -> u32 {
+        self.recoveries
+    }
+
+    /// Attach the URL the file was fetched from to the summary.
+    pub fn with_served_by(self, url: Url) -> Self {
+        Self {
+            served_by: Some(url),
+            ..self
+        }
+    }
+
+    /// Get the URL the file was fetched from, or last attempted if the
+    /// download failed.
+    ///
+    /// It is one of the URL and [mirrors](Download::mirrors) of the download,
+    /// and is not set for downloads that never reached the network.
+    pub fn served_by(&self) -> Option<&Url> {
+        self.served_by.as_ref()
+    }
+
+    /// Attach the checksum computed while downloading the file to the summary.
+    pub fn with_checksum(self, checksum: Checksum) -> Self {
+        Self {
+            checksum: Some(checksum),
+            ..self
+        }
+    }
+
+    /// Get the checksum computed while downloading the file.
+    ///
+    /// It is only computed for downloads with a
+    /// [checksum](Download::checksum) or a [hash](Download::hash) to verify,
+    /// when the file is written in order.
+    pub fn checksum(&self) -> Option<&Checksum> {
+        self.checksum.as_ref()
+    }
+
+    /// Attach the checksum the file is verified against to the summary, along
+    /// with where it comes from.
+    pub fn with_expected_checksum(self, source: ChecksumSource, checksum: Checksum) -> Self {
+        Self {
+            expected_checksum: Some((source, checksum)),
+            ..self
+        }
+    }
+
+    /// Get the checksum the file is verified against.
+    ///
+    /// It is the one of the download, or one announced by the server when
+    /// the downloader reads them, see
+    /// [`DownloaderBuilder::checksums_from_response`](crate::downloader::DownloaderBuilder::checksums_from_response).
+    pub fn expected_checksum(&self) -> Option<&Checksum> {
+        self.expected_checksum
+            .as_ref()
+            .map(|(_, checksum)| checksum)
+    }
+
+    /// Get where the checksum the file is verified against comes from.
+    pub fn checksum_source(&self) -> Option<ChecksumSource> {
+        self.expected_checksum.as_ref().map(|(source, _)| *source)
+    }
+
+    /// Attach where the file that did not match its hash was moved to.
+    pub fn with_quarantined(self, path: PathBuf) -> Self {
+        Self {
+            quarantined: Some(path),
+            ..self
+        }
+    }
+
+    /// Get where the file that did not match its hash was moved to, if it was
+    /// kept, see
+    /// [`DownloaderBuilder::mismatch_policy`](crate::downloader::DownloaderBuilder::mismatch_policy).
+    pub fn quarantined(&self) -> Option<&Path> {
+        self.quarantined.as_deref()
+    }
+
+    /// Replace the download the summary refers to.
+    pub(crate) fn with_download(self, download: Download) -> Self {
+        Self { download, ..self }
+    }
 }
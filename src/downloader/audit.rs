@@ -0,0 +1,90 @@
+//! Verification of local files without any network access.
+//!
+//! [`Downloader::audit`] runs the checks performed on existing files before
+//! downloading them, and nothing else: each file is looked up in the
+//! directory of the downloader and verified with
+//! [`Download::verify_hash`]. Files are hashed in parallel, on as many
+//! blocking threads as there are CPUs.
+//!
+//! Each download gets a [`Summary`] with one of the following statuses:
+//!
+//! | Status           | Meaning                                   |
+//! |------------------|-------------------------------------------|
+//! | `Verified`       | The file matches its hash                 |
+//! | `Missing`        | The file does not exist                   |
+//! | `HashMismatch`   | The file does not match its hash          |
+//! | `Skipped`        | The file exists but has no hash to verify |
+//! | `Fail`           | The file could not be read                |
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use trauma::downloader::DownloaderBuilder;
+//! use trauma::download::{Download, Status};
+//! use std::convert::TryFrom;
+//! use std::path::PathBuf;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut download = Download::try_from("https://example.com/file.zip")?;
+//! download.hash = Some("sha256:9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08".into());
+//!
+//! let downloader = DownloaderBuilder::new()
+//!     .directory(PathBuf::from("restored"))
+//!     .build();
+//! for summary in downloader.audit(&[download]).await {
+//!     if summary.status() != &Status::Verified {
+//!         println!("{}: {:?}", summary.filename(), summary.status());
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Downloader::audit`]: crate::downloader::Downloader::audit
+//! [`Download::verify_hash`]: crate::download::Download::verify_hash
+//! [`Summary`]: crate::download::Summary
+
+use super::downloader::{expected_hash, verify_file};
+use crate::download::integrity::ChecksumSource;
+use crate::download::{Download, Summary};
+
+use futures::stream::{self, StreamExt};
+use reqwest::StatusCode;
+use std::path::Path;
+use std::thread::available_parallelism;
+use tokio::fs;
+
+/// Verifies the files of `downloads` in `directory`, in parallel.
+///
+/// The summaries are in the order of `downloads`.
+pub(crate) async fn run(downloads: &[Download], directory: &Path) -> Vec<Summary> {
+    let workers = available_parallelism().map_or(1, |workers| workers.get());
+    stream::iter(downloads)
+        .map(|download| audit_file(download, directory))
+        .buffered(workers)
+        .collect()
+        .await
+}
+
+/// Verifies the file of `download` in `directory`.
+async fn audit_file(download: &Download, directory: &Path) -> Summary {
+    let path = directory.join(&download.filename);
+    let metadata = match fs::metadata(&path).await {
+        Ok(metadata) => metadata,
+        Err(_) => {
+            return Summary::new(download.clone(), StatusCode::NOT_FOUND, 0, false).missing();
+        }
+    };
+
+    let summary = Summary::new(download.clone(), StatusCode::OK, metadata.len(), false);
+    let summary = match download.expected_checksum() {
+        Some(expected) => summary.with_expected_checksum(ChecksumSource::Download, expected),
+        None if !download.has_hash() => return summary.skip("No hash to verify"),
+        None => summary,
+    };
+    match verify_file(download, &path).await {
+        Ok(true) => summary.verified(),
+        Ok(false) => summary.hash_mismatch(format!("expected {}", expected_hash(download))),
+        Err(e) => summary.fail(format!("Failed to verify the hash: {}", e)),
+    }
+}
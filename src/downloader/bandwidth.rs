@@ -0,0 +1,126 @@
+//! Bandwidth limiting for downloads.
+//!
+//! This module provides a token-bucket [`RateLimiter`] capping the number of
+//! bytes per second written to disk. A single limiter is shared by all the
+//! downloads of a batch to enforce the global limit set with
+//! [`DownloaderBuilder::max_bandwidth`](crate::downloader::DownloaderBuilder::max_bandwidth),
+//! and each [`Download`](crate::download::Download) may add its own limit on
+//! top of it.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use trauma::downloader::bandwidth::RateLimiter;
+//!
+//! # async fn example() {
+//! // Allow 1 MiB/s.
+//! let limiter = RateLimiter::new(1024 * 1024);
+//!
+//! // Wait until 16 KiB may be consumed.
+//! limiter.acquire(16 * 1024).await;
+//! # }
+//! ```
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// A token bucket limiting throughput to a number of bytes per second.
+///
+/// The bucket holds up to one second worth of bytes, allowing short bursts.
+/// Consumers going over the limit are put in debt and wait for it to be paid
+/// back, so concurrent consumers share the bandwidth in request order.
+#[derive(Debug)]
+pub struct RateLimiter {
+    /// Number of bytes allowed per second.
+    rate: u64,
+    /// Current state of the bucket.
+    bucket: Mutex<Bucket>,
+}
+
+/// Tokens available in a [`RateLimiter`].
+#[derive(Debug)]
+struct Bucket {
+    /// Available bytes, negative when in debt.
+    tokens: f64,
+    /// Last time the tokens were refilled.
+    updated: Instant,
+}
+
+impl RateLimiter {
+    /// Create a limiter allowing `bytes_per_sec` bytes per second.
+    ///
+    /// A rate of zero is raised to one byte per second.
+    pub fn new(bytes_per_sec: u64) -> Self {
+        let rate = bytes_per_sec.max(1);
+        Self {
+            rate,
+            bucket: Mutex::new(Bucket {
+                tokens: rate as f64,
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    /// Number of bytes allowed per second.
+    pub fn rate(&self) -> u64 {
+        self.rate
+    }
+
+    /// Wait until `bytes` bytes may be consumed.
+    pub async fn acquire(&self, bytes: u64) {
+        let delay = self.reserve(bytes);
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Consume `bytes` bytes and return how long to wait before using them.
+    pub fn reserve(&self, bytes: u64) -> Duration {
+        let rate = self.rate as f64;
+        let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+
+        // Refill the bucket with the tokens earned since the last update.
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(rate);
+        bucket.updated = now;
+
+        bucket.tokens -= bytes as f64;
+        match bucket.tokens < 0.0 {
+            true => Duration::from_secs_f64(-bucket.tokens / rate),
+            false => Duration::ZERO,
+        }
+    }
+}
+
+/// The limiters applying to a single download.
+#[derive(Debug, Default)]
+pub(crate) struct Throttle<'a> {
+    /// Limiter shared by the whole batch.
+    global: Option<&'a RateLimiter>,
+    /// Limiter specific to the download.
+    local: Option<RateLimiter>,
+}
+
+impl<'a> Throttle<'a> {
+    /// Combine the limiter of the batch with the limit of a download.
+    pub(crate) fn new(global: Option<&'a RateLimiter>, local: Option<u64>) -> Self {
+        Self {
+            global,
+            local: local.map(RateLimiter::new),
+        }
+    }
+
+    /// Wait until `bytes` bytes may be consumed under every limit.
+    pub(crate) async fn acquire(&self, bytes: u64) {
+        let delay = [self.global, self.local.as_ref()]
+            .into_iter()
+            .flatten()
+            .map(|limiter| limiter.reserve(bytes))
+            .max()
+            .unwrap_or_default();
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
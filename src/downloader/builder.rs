@@ -54,12 +54,19 @@
 //! let downloader = DownloaderBuilder::hidden().build();
 //! ```
 
-use super::{config::DownloaderConfig, downloader::Downloader};
+use super::{
+    config::DownloaderConfig,
+    downloader::Downloader,
+    manager::DownloadManager,
+    scheduler::SchedulingStrategy,
+    verification::{MismatchPolicy, VerificationPolicy},
+    watchdog::LowSpeedLimit,
+};
 use crate::download::Summary;
 use crate::{ProgressBarOpts, StyleOptions};
 
 use reqwest::header::{HeaderMap, HeaderValue, IntoHeaderName};
-use std::{path::PathBuf, sync::Arc};
+use std::{path::PathBuf, sync::Arc, time::Duration};
 
 /// A builder used to create a [`Downloader`].
 ///
@@ -173,6 +180,171 @@ impl DownloaderBuilder {
         self
     }
 
+    /// Split each resumable file into `segments` byte ranges fetched in parallel.
+    ///
+    /// Segmented downloads are only used when the server accepts range requests
+    /// and reports the content length. Each segment is at least 1 MiB long, so
+    /// small files are still fetched over a single connection. A value of `1`
+    /// (the default) disables segmentation.
+    pub fn segments(mut self, segments: usize) -> Self {
+        self.config.segments = segments.max(1);
+        self
+    }
+
+    /// Write a resume journal next to each partial download.
+    ///
+    /// The journal (`<filename>.trauma.json`) records the URL, validators,
+    /// expected size and progress of the download, so that a later run, even in
+    /// another process, can safely resume it. It is removed once the download
+    /// succeeds. See [`journal`](crate::download::journal) for its format.
+    ///
+    /// A journal is written regardless for the downloads whose server sent
+    /// validators, as it is where they are kept for resume validation.
+    pub fn journal(mut self, journal: bool) -> Self {
+        self.config.journal = journal;
+        self
+    }
+
+    /// Limit the bandwidth of each batch of downloads, in bytes per second.
+    ///
+    /// The limit is shared by all the downloads running concurrently,
+    /// including the segments of a segmented download. A per-download limit
+    /// can be added with [`Download::max_bandwidth`](crate::download::Download::max_bandwidth).
+    pub fn max_bandwidth(mut self, bytes_per_sec: u64) -> Self {
+        self.config.max_bandwidth = Some(bytes_per_sec);
+        self
+    }
+
+    /// Set how pending downloads of equal priority are ordered.
+    ///
+    /// Downloads with a higher [`Download::priority`](crate::download::Download::priority)
+    /// are always started first.
+    pub fn scheduling(mut self, strategy: SchedulingStrategy) -> Self {
+        self.config.scheduling = strategy;
+        self
+    }
+
+    /// Limit the number of connections opened to the same host.
+    ///
+    /// Downloads from a host at its limit wait in the queue while the ones
+    /// from other hosts keep starting. The segments of a segmented download
+    /// count as connections: a download never uses more segments than the
+    /// limit, and only fetches as many of them at once as its host has
+    /// connections to spare.
+    pub fn max_connections_per_host(mut self, connections: usize) -> Self {
+        self.config.max_connections_per_host = Some(connections.max(1));
+        self
+    }
+
+    /// Wait at least `delay` between two requests to the same host.
+    ///
+    /// Requests to other hosts are sent without waiting.
+    pub fn host_delay(mut self, delay: Duration) -> Self {
+        self.config.host_delay = Some(delay);
+        self
+    }
+
+    /// Set how many times a transfer is resumed after losing its connection.
+    ///
+    /// When the body of a response fails halfway through and the server
+    /// accepts range requests, the file is requested again from the last byte
+    /// written instead of failing the download. A value of `0` disables the
+    /// recovery. Defaults to 3.
+    pub fn recovery_attempts(mut self, attempts: u32) -> Self {
+        self.config.recovery_attempts = attempts;
+        self
+    }
+
+    /// Give up on a connection that cannot be established within `timeout`.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.config.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Consider a transfer stalled when no data arrives within `timeout`.
+    ///
+    /// A stalled transfer is resumed like a lost connection, see
+    /// [`DownloaderBuilder::recovery_attempts`]. It can be overridden per
+    /// download with [`Download::idle_timeout`](crate::download::Download::idle_timeout).
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.config.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Fail any download still running `deadline` after it started.
+    ///
+    /// It can be overridden per download with
+    /// [`Download::deadline`](crate::download::Download::deadline).
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.config.deadline = Some(deadline);
+        self
+    }
+
+    /// Abort transfers slower than `bytes_per_sec` for `duration`.
+    ///
+    /// An aborted transfer is resumed like a lost connection, see
+    /// [`DownloaderBuilder::recovery_attempts`]. The limit should stay below
+    /// any bandwidth limit, which would otherwise trigger it.
+    pub fn low_speed_limit(mut self, bytes_per_sec: u64, duration: Duration) -> Self {
+        self.config.low_speed_limit = Some(LowSpeedLimit::new(bytes_per_sec, duration));
+        self
+    }
+
+    /// Set whether to name the files after the responses of the servers.
+    ///
+    /// The filename of each download is taken from the `Content-Disposition`
+    /// header of the response to the file request, then from the last segment
+    /// of the URL after redirects. The filename the download was created with
+    /// is kept when neither gives a usable name, and for ZIP extractions. It
+    /// also names the partial file, so that a later run can resume it before
+    /// asking the server. The chosen name is available with
+    /// [`Summary::filename`](crate::download::Summary::filename).
+    pub fn filename_from_response(mut self, enabled: bool) -> Self {
+        self.config.filename_from_response = enabled;
+        self
+    }
+
+    /// Set what to do with downloaded files that do not match their hash.
+    ///
+    /// Downloads with a [checksum](crate::download::Download::checksum) or a
+    /// [hash](crate::download::Download::hash) are verified once complete,
+    /// before being moved into place, and reported as a
+    /// [`HashMismatch`](crate::download::Status::HashMismatch) if they do not
+    /// match. Mismatching files never replace the destination, and are
+    /// deleted by default.
+    pub fn verification(mut self, policy: VerificationPolicy) -> Self {
+        self.config.verification = policy;
+        self
+    }
+
+    /// Set what to do with files that do not match their hash.
+    ///
+    /// Unless overwriting, a file that already exists is verified against the
+    /// hash of its download before being downloaded again, and so is every
+    /// downloaded file before being moved into place. Mismatching files are
+    /// deleted by default. The new location of the ones that are moved is
+    /// reported by their [`HashMismatch`](crate::download::Status::HashMismatch)
+    /// summary, see
+    /// [`Summary::quarantined`](crate::download::Summary::quarantined).
+    pub fn mismatch_policy(mut self, policy: MismatchPolicy) -> Self {
+        self.config.mismatch_policy = policy;
+        self
+    }
+
+    /// Set whether to verify downloads without a hash against the digests
+    /// sent by the servers.
+    ///
+    /// The digests are read from the `Repr-Digest`, `Digest`, `x-goog-hash`
+    /// and `Content-MD5` headers of the responses, see
+    /// [`integrity`](crate::download::integrity). Files that do not match are
+    /// handled like those that do not match their own hash, and
+    /// [`Summary::checksum_source`](crate::download::Summary::checksum_source)
+    /// tells which header they were verified against.
+    pub fn checksums_from_response(mut self, enabled: bool) -> Self {
+        self.config.checksums_from_response = enabled;
+        self
+    }
+
     /// Helper method to get or create a new HeaderMap.
     fn new_header(&self) -> HeaderMap {
         match self.config.headers {
@@ -251,4 +423,12 @@ impl DownloaderBuilder {
     pub fn build(self) -> Downloader {
         Downloader::new(self.config)
     }
+
+    /// Create a [`DownloadManager`] running in the background with the
+    /// specified options.
+    ///
+    /// This must be called from within a Tokio runtime.
+    pub fn build_manager(self) -> DownloadManager {
+        DownloadManager::new(self.build(), None)
+    }
 }
@@ -38,17 +38,25 @@
 //!     retries: 5,
 //!     proxy: None,
 //!     headers: Some(headers),
+//!     host_delay: None,
+//!     connect_timeout: None,
+//!     read_timeout: None,
+//!     timeout: None,
 //! };
 //! # Ok(())
 //! # }
 //! ```
 
+use super::scheduler::SchedulingStrategy;
+use super::verification::{MismatchPolicy, VerificationPolicy};
+use super::watchdog::LowSpeedLimit;
 use crate::download::Summary;
 use crate::StyleOptions;
 
 use reqwest::header::HeaderMap;
 use std::env::current_dir;
 use std::sync::Arc;
+use std::time::Duration;
 
 /// Callback type for download completion events
 pub type DownloadCallback = Box<dyn Fn(&Summary) + Send + Sync>;
@@ -87,6 +95,36 @@ pub struct DownloaderConfig {
     pub on_complete: Option<Arc<DownloadCallback>>,
     /// Force download and overwrite existing files.
     pub overwrite: bool,
+    /// Number of parallel range requests used to fetch a single resumable file.
+    pub segments: usize,
+    /// Write a resume journal next to each partial download.
+    pub journal: bool,
+    /// Bandwidth shared by all the downloads of a batch, in bytes per second.
+    pub max_bandwidth: Option<u64>,
+    /// How pending downloads of equal priority are ordered.
+    pub scheduling: SchedulingStrategy,
+    /// Maximum number of connections opened to the same host.
+    pub max_connections_per_host: Option<usize>,
+    /// Minimum delay between two requests to the same host.
+    pub host_delay: Option<Duration>,
+    /// Number of times a transfer is resumed after losing its connection.
+    pub recovery_attempts: u32,
+    /// Maximum time to establish a connection.
+    pub connect_timeout: Option<Duration>,
+    /// Maximum time to wait for data before a transfer is considered stalled.
+    pub idle_timeout: Option<Duration>,
+    /// Maximum duration of each download, from its first request to its end.
+    pub deadline: Option<Duration>,
+    /// Minimum throughput of each transfer.
+    pub low_speed_limit: Option<LowSpeedLimit>,
+    /// Name the files after the responses of the servers.
+    pub filename_from_response: bool,
+    /// What to do with downloaded files that do not match their hash.
+    pub verification: VerificationPolicy,
+    /// Verify downloads without a hash against the digests sent by the servers.
+    pub checksums_from_response: bool,
+    /// What to do with existing files that do not match their hash.
+    pub mismatch_policy: MismatchPolicy,
 }
 
 impl std::fmt::Debug for DownloaderConfig {
@@ -105,6 +143,21 @@ impl std::fmt::Debug for DownloaderConfig {
             .field("single_file_progress", &self.single_file_progress)
             .field("on_complete", &self.on_complete.is_some())
             .field("overwrite", &self.overwrite)
+            .field("segments", &self.segments)
+            .field("journal", &self.journal)
+            .field("max_bandwidth", &self.max_bandwidth)
+            .field("scheduling", &self.scheduling)
+            .field("max_connections_per_host", &self.max_connections_per_host)
+            .field("host_delay", &self.host_delay)
+            .field("recovery_attempts", &self.recovery_attempts)
+            .field("connect_timeout", &self.connect_timeout)
+            .field("idle_timeout", &self.idle_timeout)
+            .field("deadline", &self.deadline)
+            .field("low_speed_limit", &self.low_speed_limit)
+            .field("filename_from_response", &self.filename_from_response)
+            .field("verification", &self.verification)
+            .field("checksums_from_response", &self.checksums_from_response)
+            .field("mismatch_policy", &self.mismatch_policy)
             .finish()
     }
 }
@@ -122,6 +175,21 @@ impl Default for DownloaderConfig {
             single_file_progress: false,
             on_complete: None,
             overwrite: false,
+            segments: 1,
+            journal: false,
+            max_bandwidth: None,
+            scheduling: SchedulingStrategy::default(),
+            max_connections_per_host: None,
+            host_delay: None,
+            recovery_attempts: 3,
+            connect_timeout: None,
+            idle_timeout: None,
+            deadline: None,
+            low_speed_limit: None,
+            filename_from_response: false,
+            verification: VerificationPolicy::default(),
+            checksums_from_response: false,
+            mismatch_policy: MismatchPolicy::default(),
         }
     }
 }
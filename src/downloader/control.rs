@@ -0,0 +1,235 @@
+//! Cancellation and pause/resume of running downloads.
+//!
+//! [`Downloader::start`](crate::downloader::Downloader::start) returns a
+//! [`DownloadHandle`] alongside the batch future. The handle controls either
+//! the whole batch or an individual download, identified by its index in the
+//! slice given to the downloader.
+//!
+//! Pausing a download stops reading from its connection until it is resumed.
+//! Cancelling it stops it for good: its [`Summary`](crate::download::Summary)
+//! reports [`Status::Cancelled`](crate::download::Status::Cancelled) and the
+//! data received so far is kept in its partial file so that a later run can
+//! resume it.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use trauma::downloader::DownloaderBuilder;
+//! use trauma::download::Download;
+//! use std::convert::TryFrom;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let downloads = vec![
+//!     Download::try_from("https://example.com/file1.zip")?,
+//!     Download::try_from("https://example.com/file2.zip")?,
+//! ];
+//! let downloader = DownloaderBuilder::new().build();
+//! let (handle, batch) = downloader.start(&downloads, None);
+//!
+//! // Stop the second download, and pause the others for a while.
+//! handle.cancel_download(1);
+//! handle.pause();
+//! handle.resume();
+//!
+//! let summaries = batch.await;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::future;
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// Requested state of a batch or of a download.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum State {
+    /// Keep downloading.
+    Running,
+    /// Stop reading until resumed.
+    Paused,
+    /// Stop for good.
+    Cancelled,
+}
+
+/// State shared between a [`DownloadHandle`] and its batch.
+#[derive(Debug)]
+struct Shared {
+    /// State of the whole batch.
+    batch: watch::Sender<State>,
+    /// State of each download of the batch.
+    downloads: Vec<watch::Sender<State>>,
+}
+
+/// Controls a running batch of downloads.
+///
+/// The handle can be cloned and sent to other tasks. Dropping it does not
+/// affect the batch.
+#[derive(Debug, Clone)]
+pub struct DownloadHandle {
+    shared: Arc<Shared>,
+}
+
+impl DownloadHandle {
+    /// Create a handle for a batch of `count` downloads.
+    pub(crate) fn new(count: usize) -> Self {
+        let shared = Shared {
+            batch: watch::Sender::new(State::Running),
+            downloads: (0..count)
+                .map(|_| watch::Sender::new(State::Running))
+                .collect(),
+        };
+        Self {
+            shared: Arc::new(shared),
+        }
+    }
+
+    /// Get the control of the download at `index`.
+    pub(crate) fn control(&self, index: usize) -> Control {
+        Control {
+            _shared: self.shared.clone(),
+            batch: self.shared.batch.subscribe(),
+            download: self.shared.downloads[index].subscribe(),
+        }
+    }
+
+    /// Cancel every download of the batch.
+    pub fn cancel(&self) {
+        self.shared.batch.send_replace(State::Cancelled);
+    }
+
+    /// Pause every download of the batch.
+    ///
+    /// Downloads that were cancelled stay cancelled.
+    pub fn pause(&self) {
+        set_unless_cancelled(&self.shared.batch, State::Paused);
+    }
+
+    /// Resume the batch after a call to [`DownloadHandle::pause`].
+    ///
+    /// Downloads paused individually stay paused.
+    pub fn resume(&self) {
+        set_unless_cancelled(&self.shared.batch, State::Running);
+    }
+
+    /// Get whether the whole batch was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        *self.shared.batch.borrow() == State::Cancelled
+    }
+
+    /// Get whether the whole batch is paused.
+    pub fn is_paused(&self) -> bool {
+        *self.shared.batch.borrow() == State::Paused
+    }
+
+    /// Cancel the download at `index`.
+    ///
+    /// Returns `false` if there is no download at `index`.
+    pub fn cancel_download(&self, index: usize) -> bool {
+        self.set_download(index, State::Cancelled)
+    }
+
+    /// Pause the download at `index`.
+    ///
+    /// Returns `false` if there is no download at `index`.
+    pub fn pause_download(&self, index: usize) -> bool {
+        self.set_download(index, State::Paused)
+    }
+
+    /// Resume the download at `index` after a call to
+    /// [`DownloadHandle::pause_download`].
+    ///
+    /// Returns `false` if there is no download at `index`.
+    pub fn resume_download(&self, index: usize) -> bool {
+        self.set_download(index, State::Running)
+    }
+
+    /// Update the state of the download at `index`.
+    fn set_download(&self, index: usize, state: State) -> bool {
+        match self.shared.downloads.get(index) {
+            Some(sender) => {
+                set_unless_cancelled(sender, state);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Update `sender`, unless it was already cancelled.
+fn set_unless_cancelled(sender: &watch::Sender<State>, state: State) {
+    sender.send_if_modified(|current| {
+        let modified = *current != State::Cancelled && *current != state;
+        if modified {
+            *current = state;
+        }
+        modified
+    });
+}
+
+/// The state of a single download, as seen from the downloader.
+#[derive(Debug, Clone)]
+pub(crate) struct Control {
+    /// Keeps the senders alive for as long as the download runs.
+    _shared: Arc<Shared>,
+    /// State of the batch.
+    batch: watch::Receiver<State>,
+    /// State of the download.
+    download: watch::Receiver<State>,
+}
+
+impl Control {
+    /// The effective state of the download.
+    fn state(&self) -> State {
+        (*self.batch.borrow()).max(*self.download.borrow())
+    }
+
+    /// Get whether the download was cancelled.
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.state() == State::Cancelled
+    }
+
+    /// Get whether the download is paused.
+    pub(crate) fn is_paused(&self) -> bool {
+        self.state() == State::Paused
+    }
+
+    /// Wait while the download is paused.
+    ///
+    /// Returns `false` if the download was cancelled.
+    pub(crate) async fn proceed(&mut self) -> bool {
+        loop {
+            match self.state() {
+                State::Running => return true,
+                State::Cancelled => return false,
+                State::Paused => self.changed().await,
+            }
+        }
+    }
+
+    /// Wait until the download is cancelled.
+    pub(crate) async fn cancelled(&mut self) {
+        while !self.is_cancelled() {
+            self.changed().await;
+        }
+    }
+
+    /// Wait until the download is paused or cancelled.
+    pub(crate) async fn stopped(&mut self) {
+        while self.state() == State::Running {
+            self.changed().await;
+        }
+    }
+
+    /// Wait until the state of the batch or of the download changes.
+    async fn changed(&mut self) {
+        let changed = tokio::select! {
+            changed = self.batch.changed() => changed,
+            changed = self.download.changed() => changed,
+        };
+
+        // The senders live as long as the control, this cannot happen.
+        if changed.is_err() {
+            future::pending::<()>().await;
+        }
+    }
+}
@@ -45,23 +45,55 @@
 //! # }
 //! ```
 
+use super::audit;
+use super::bandwidth::{RateLimiter, Throttle};
 use super::config::DownloaderConfig;
-use crate::download::{Download, Status, Summary};
+use super::control::{Control, DownloadHandle};
+use super::event::{DownloadEvent, Events};
+use super::scheduler::{HostSlots, PendingQueue, SchedulingStrategy};
+use super::segment::{segment_count, split_into_segments, Segment};
+use super::sync::{self, SyncReport};
+use super::verification::{MismatchPolicy, VerificationPolicy, BAD_SUFFIX};
+use super::watchdog::{LowSpeedLimit, Watchdog};
+use crate::download::checksum::{Algorithm, Checksum, Hasher};
+use crate::download::integrity::{response_checksum, ChecksumSource};
+use crate::download::journal::{journal_path, Journal, SegmentState, CHECKPOINT_INTERVAL};
+use crate::download::resume::if_range_value;
+use crate::download::{Download, ResumeOutcome, Status, Summary};
+use crate::error::{Error, TimeoutKind};
 use crate::http::{create_http_client, HttpClientConfig};
 use crate::progress::display::ProgressDisplay;
-use crate::utils::content_length::get_content_length;
+use crate::utils::content_length::{
+    get_content_length, parse_content_range_start, parse_content_range_total,
+};
+use crate::utils::filename::{filename_from_url, parse_content_disposition};
+use crate::utils::path::{append_extension, part_path};
 use crate::archive::zip::ZipExtractor;
 
-use futures::stream::{self, StreamExt};
+use futures::stream::{self, FuturesUnordered, Stream, StreamExt};
+use indicatif::ProgressBar;
 use reqwest::{
-    header::{HeaderMap, RANGE},
-    StatusCode,
+    header::{
+        HeaderMap, HeaderValue, ACCEPT_RANGES, CONTENT_DISPOSITION, CONTENT_RANGE, IF_RANGE, RANGE,
+    },
+    Response, StatusCode,
 };
-use reqwest_middleware::ClientWithMiddleware;
+use reqwest_middleware::{ClientWithMiddleware, RequestBuilder};
 use std::fmt;
 use std::fmt::Debug;
-use std::path::PathBuf;
-use tokio::{fs, fs::OpenOptions, io::AsyncWriteExt};
+use std::future::{self, Future};
+use std::io::{self, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::{
+    fs,
+    fs::OpenOptions,
+    io::{AsyncSeekExt, AsyncWriteExt},
+    sync::{mpsc, Mutex},
+    time::Instant,
+};
 use tracing::debug;
 
 /// Represents the download controller.
@@ -134,6 +166,82 @@ impl Downloader {
         self.config.overwrite
     }
 
+    /// Gets the number of segments used for a single resumable file.
+    pub fn segments(&self) -> usize {
+        self.config.segments
+    }
+
+    /// Gets whether a resume journal is written next to each partial download.
+    pub fn journal(&self) -> bool {
+        self.config.journal
+    }
+
+    /// Gets the bandwidth limit shared by all downloads, in bytes per second.
+    pub fn max_bandwidth(&self) -> Option<u64> {
+        self.config.max_bandwidth
+    }
+
+    /// Gets how pending downloads of equal priority are ordered.
+    pub fn scheduling(&self) -> SchedulingStrategy {
+        self.config.scheduling
+    }
+
+    /// Gets the maximum number of connections opened to the same host.
+    pub fn max_connections_per_host(&self) -> Option<usize> {
+        self.config.max_connections_per_host
+    }
+
+    /// Gets the minimum delay between two requests to the same host.
+    pub fn host_delay(&self) -> Option<Duration> {
+        self.config.host_delay
+    }
+
+    /// Gets how many times a transfer is resumed after losing its connection.
+    pub fn recovery_attempts(&self) -> u32 {
+        self.config.recovery_attempts
+    }
+
+    /// Gets the maximum time to establish a connection.
+    pub fn connect_timeout(&self) -> Option<Duration> {
+        self.config.connect_timeout
+    }
+
+    /// Gets the maximum time to wait for data before a transfer is considered stalled.
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        self.config.idle_timeout
+    }
+
+    /// Gets the maximum duration of each download.
+    pub fn deadline(&self) -> Option<Duration> {
+        self.config.deadline
+    }
+
+    /// Gets the minimum throughput of each transfer.
+    pub fn low_speed_limit(&self) -> Option<LowSpeedLimit> {
+        self.config.low_speed_limit
+    }
+
+    /// Gets whether files are named after the responses of the servers.
+    pub fn filename_from_response(&self) -> bool {
+        self.config.filename_from_response
+    }
+
+    /// Gets what is done with downloaded files that do not match their hash.
+    pub fn verification(&self) -> VerificationPolicy {
+        self.config.verification
+    }
+
+    /// Gets whether downloads without a hash are verified against the digests
+    /// sent by the servers.
+    pub fn checksums_from_response(&self) -> bool {
+        self.config.checksums_from_response
+    }
+
+    /// Gets what is done with existing files that do not match their hash.
+    pub fn mismatch_policy(&self) -> &MismatchPolicy {
+        &self.config.mismatch_policy
+    }
+
     /// Starts the downloads with optional proxy.
     pub async fn download(
         &self,
@@ -149,38 +257,208 @@ impl Downloader {
         downloads: &[Download],
         proxy: Option<reqwest::Proxy>,
     ) -> Vec<Summary> {
-        // Prepare the HTTP client using the new HTTP module.
+        self.run(downloads, proxy, DownloadHandle::new(downloads.len()), None)
+            .await
+    }
+
+    /// Verifies the files of `downloads` in the download directory, without
+    /// any network access.
+    ///
+    /// Returns a summary per download, in the order of `downloads`, marked as
+    /// [`Verified`](Status::Verified), [`Missing`](Status::Missing) or
+    /// [`HashMismatch`](Status::HashMismatch). See [`audit`] for details.
+    pub async fn audit(&self, downloads: &[Download]) -> Vec<Summary> {
+        audit::run(downloads, &self.config.directory).await
+    }
+
+    /// Synchronizes `directory` with `downloads`.
+    ///
+    /// Only the files that are missing from `directory` or do not match their
+    /// hash are downloaded. With `prune`, the files of `directory` that are not
+    /// part of `downloads` are deleted. See [`sync`] for details.
+    pub async fn sync(
+        &self,
+        downloads: &[Download],
+        directory: &Path,
+        prune: bool,
+        proxy: Option<reqwest::Proxy>,
+    ) -> SyncReport {
+        // Outdated files are already known, and files must keep their names
+        // to be told apart from the ones to prune.
+        let downloader = Downloader::new(DownloaderConfig {
+            directory: directory.to_path_buf(),
+            overwrite: true,
+            filename_from_response: false,
+            ..self.config.clone()
+        });
+        sync::run(&downloader, downloads, prune, proxy).await
+    }
+
+    /// Starts the downloads and returns a handle to control them.
+    ///
+    /// The downloads only make progress while the returned future is polled.
+    /// The [`DownloadHandle`] can cancel, pause or resume the whole batch, or
+    /// a single download given its index in `downloads`.
+    pub fn start<'a>(
+        &'a self,
+        downloads: &'a [Download],
+        proxy: Option<reqwest::Proxy>,
+    ) -> (DownloadHandle, impl Future<Output = Vec<Summary>> + 'a) {
+        let handle = DownloadHandle::new(downloads.len());
+        let batch = self.run(downloads, proxy, handle.clone(), None);
+        (handle, batch)
+    }
+
+    /// Starts the downloads in the background and returns a stream of their
+    /// events.
+    ///
+    /// The batch is spawned on the current Tokio runtime and every download
+    /// ends with a [`DownloadEvent::Finished`] event holding its [`Summary`].
+    /// The stream ends once the batch is over. Dropping the stream does not
+    /// stop the downloads, use the [`DownloadHandle`] to cancel them.
+    pub fn download_stream(
+        &self,
+        downloads: Vec<Download>,
+        proxy: Option<reqwest::Proxy>,
+    ) -> (DownloadHandle, impl Stream<Item = DownloadEvent>) {
+        let handle = DownloadHandle::new(downloads.len());
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        let downloader = self.clone();
+        let batch = handle.clone();
+        tokio::spawn(async move {
+            downloader.run(&downloads, proxy, batch, Some(sender)).await;
+        });
+
+        let events = stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|event| (event, receiver))
+        });
+        (handle, events)
+    }
+
+    /// Runs a batch of downloads controlled by `handle`, sending their events
+    /// to `events` if provided.
+    async fn run(
+        &self,
+        downloads: &[Download],
+        proxy: Option<reqwest::Proxy>,
+        handle: DownloadHandle,
+        events: Option<mpsc::UnboundedSender<DownloadEvent>>,
+    ) -> Vec<Summary> {
+        let client = self.http_client(proxy);
+        let progress_display = self.progress_display(downloads.len());
+
+        // All the downloads of the batch share the same bandwidth.
+        let bandwidth = self.config.max_bandwidth.map(RateLimiter::new);
+
+        // Every download is queued until a slot is available.
+        let mut queue = PendingQueue::new(self.config.scheduling);
+        let sizes = match queue.needs_size() {
+            true => self.probe_sizes(&client, downloads).await,
+            false => vec![None; downloads.len()],
+        };
+        for (index, (d, size)) in downloads.iter().zip(sizes).enumerate() {
+            let events = Events::new(events.clone(), index);
+            events.queued();
+            queue.push((index, events), d.priority, size);
+        }
+
+        // Download the files asynchronously, starting the next scheduled one
+        // whenever a slot frees up and its host accepts another connection.
+        let hosts = HostSlots::new(self.config.max_connections_per_host);
+        let mut running = FuturesUnordered::new();
+        let mut summaries = Vec::with_capacity(downloads.len());
+        loop {
+            while running.len() < self.config.concurrent_downloads {
+                let Some((index, events)) =
+                    queue.pop(|(index, _)| hosts.available(&downloads[*index].url))
+                else {
+                    break;
+                };
+                let d = &downloads[index];
+                hosts.acquire(&d.url);
+                let mut ctx = Context {
+                    client: &client,
+                    progress_display: &progress_display,
+                    throttle: Throttle::new(bandwidth.as_ref(), d.max_bandwidth),
+                    hosts: &hosts,
+                    control: handle.control(index),
+                    events,
+                };
+                running.push(async move {
+                    let summary = self.fetch(d, &mut ctx).await;
+                    ctx.events.finished(&summary);
+                    summary
+                });
+            }
+
+            match running.next().await {
+                Some(summary) => {
+                    hosts.release(&summary.download().url);
+                    summaries.push(summary);
+                }
+                None => break,
+            }
+        }
+        drop(running);
+
+        // Finish the progress display.
+        progress_display.finish();
+
+        // Return the download summaries.
+        summaries
+    }
+
+    /// Prepare the HTTP client using the new HTTP module.
+    pub(crate) fn http_client(&self, proxy: Option<reqwest::Proxy>) -> ClientWithMiddleware {
         let config = HttpClientConfig {
             retries: self.config.retries,
             proxy,
             headers: self.config.headers.clone(),
+            host_delay: self.config.host_delay,
+            connect_timeout: self.config.connect_timeout,
+            // Stalled transfers are detected per download, see `next_chunk`.
+            read_timeout: None,
+            timeout: None,
         };
 
-        let client = create_http_client(config).unwrap();
+        create_http_client(config).unwrap()
+    }
 
-        // Prepare the progress display.
-        let progress_display = ProgressDisplay::new(
+    /// Prepare the progress display for `total` downloads.
+    pub(crate) fn progress_display(&self, total: usize) -> ProgressDisplay {
+        ProgressDisplay::new(
             self.config.style_options.clone(),
-            downloads.len(),
+            total,
             self.config.single_file_progress,
-        );
-
-        // Download the files asynchronously.
-        let summaries = stream::iter(downloads)
-            .map(|d| self.fetch(&client, d, &progress_display))
-            .buffer_unordered(self.config.concurrent_downloads)
-            .collect::<Vec<_>>()
-            .await;
-
-        // Finish the progress display.
-        progress_display.finish();
+        )
+    }
 
-        // Return the download summaries.
-        summaries
+    /// Probe the size of each download, concurrently.
+    async fn probe_sizes(
+        &self,
+        client: &ClientWithMiddleware,
+        downloads: &[Download],
+    ) -> Vec<Option<u64>> {
+        stream::iter(0..downloads.len())
+            .map(|index| async move {
+                let download = &downloads[index];
+                match download.size {
+                    Some(size) => Some(size),
+                    None => self
+                        .get_content_length(client, download)
+                        .await
+                        .ok()
+                        .flatten(),
+                }
+            })
+            .buffered(self.config.concurrent_downloads)
+            .collect()
+            .await
     }
 
     /// Get content length using either HEAD request or Range request based on configuration.
-    async fn get_content_length(
+    pub(crate) async fn get_content_length(
         &self,
         client: &ClientWithMiddleware,
         download: &Download,
@@ -200,98 +478,311 @@ impl Downloader {
         }
     }
 
-    /// Fetches the files and write them to disk.
-    async fn fetch(
-        &self,
-        client: &ClientWithMiddleware,
-        download: &Download,
-        progress_display: &ProgressDisplay,
-    ) -> Summary {
+    /// Fetches the files and write them to disk, within the deadline of the download.
+    pub(crate) async fn fetch(&self, download: &Download, ctx: &mut Context<'_>) -> Summary {
+        let Some(deadline) = download.deadline.or(self.config.deadline) else {
+            return self.fetch_file(download, ctx).await;
+        };
+
+        match tokio::time::timeout(deadline, self.fetch_file(download, ctx)).await {
+            Ok(summary) => summary,
+            Err(_) => {
+                debug!("Download of {} missed its deadline", &download.url);
+                let summary = Summary::new(download.clone(), StatusCode::BAD_REQUEST, 0, false);
+                self.report(summary.time_out(TimeoutKind::Deadline))
+            }
+        }
+    }
+
+    /// Fetches a file and write it to disk.
+    async fn fetch_file(&self, download: &Download, ctx: &mut Context<'_>) -> Summary {
+        // Wait while paused, and never start a cancelled download.
+        if !ctx.control.proceed().await {
+            let summary = Summary::new(download.clone(), StatusCode::BAD_REQUEST, 0, false);
+            return self.report(summary.cancel());
+        }
+
+        // A hash of unknown type could never be verified.
+        if download.has_hash() && download.expected_checksum().is_none() {
+            let summary = Summary::new(download.clone(), StatusCode::BAD_REQUEST, 0, false);
+            return self.report(summary.fail("unrecognized hash format"));
+        }
+
         let file_path = self.config.directory.join(&download.filename);
 
         // Check if file exists and hash matches
         if !self.config.overwrite && file_path.exists() {
-            match download.verify_hash(&file_path) {
-                Ok(true) => {
+            if download.has_hash() {
+                ctx.events.verifying();
+            }
+            // Only keep the outcome.
+            match verify_file(download, &file_path).await.ok() {
+                Some(true) => {
                     let file_size = fs::metadata(&file_path).await.map(|m| m.len()).unwrap_or(0);
 
                     return Summary::new(download.clone(), StatusCode::OK, file_size, false)
                         .skip("File exists with matching hash");
                 }
-                Ok(false) => {
-                    // Hash verification failed - delete the file and trigger callback
+                Some(false) => {
+                    // Hash verification failed - get rid of the file and trigger callback
                     let file_size = fs::metadata(&file_path).await.map(|m| m.len()).unwrap_or(0);
-
-                    let hash_mismatch_summary =
-                        Summary::new(download.clone(), StatusCode::OK, file_size, false)
-                            .hash_mismatch("Hash mismatch, redownloading file");
-
-                    // Call the callback for hash mismatch
-                    if let Some(ref callback) = self.config.on_complete {
-                        callback(&hash_mismatch_summary);
-                    }
-
-                    if let Err(e) = fs::remove_file(&file_path).await {
+                    let destination = self.config.mismatch_policy.destination(&file_path);
+                    let removed = match destination {
+                        Some(ref destination) => move_file(&file_path, destination)
+                            .await
+                            .map_err(|e| format!("Failed to move file with wrong hash: {}", e)),
+                        None => fs::remove_file(&file_path)
+                            .await
+                            .map_err(|e| format!("Failed to remove file with wrong hash: {}", e)),
+                    };
+                    if let Err(e) = removed {
                         return Summary::new(
                             download.clone(),
                             StatusCode::INTERNAL_SERVER_ERROR,
                             0,
                             false,
                         )
-                        .fail(format!("Failed to remove file with wrong hash: {}", e));
+                        .fail(e);
+                    }
+
+                    let summary = Summary::new(download.clone(), StatusCode::OK, file_size, false);
+                    let hash_mismatch_summary = match destination {
+                        Some(destination) => summary
+                            .hash_mismatch(format!(
+                                "Hash mismatch, moved file to {:?}, redownloading file",
+                                &destination
+                            ))
+                            .with_quarantined(destination),
+                        None => summary.hash_mismatch("Hash mismatch, redownloading file"),
+                    };
+
+                    // Call the callback for hash mismatch
+                    if let Some(ref callback) = self.config.on_complete {
+                        callback(&hash_mismatch_summary);
                     }
                 }
-                Err(_) => {
+                None => {
                     // Error calculating hash, continue to download
                 }
             }
         }
 
-        // Check if this is a ZIP extraction request
-        if download.is_extraction() {
-            return self.extract_from_zip(client, download, progress_display).await;
+        let mut attempt = 0;
+        loop {
+            // Check if this is a ZIP extraction request
+            let summary = if download.is_extraction() {
+                ctx.events.extracting();
+                self.extract_from_zip(download, ctx).await
+            } else {
+                self.fetch_mirrors(download, ctx).await
+            };
+
+            match (summary.status(), self.config.verification) {
+                (Status::HashMismatch(_), VerificationPolicy::Retry(retries))
+                    if attempt < retries =>
+                {
+                    attempt += 1;
+                    debug!("Downloading {} again after a hash mismatch", &download.url);
+                    ctx.events.retrying(attempt, "hash mismatch");
+                }
+                _ => return self.report(summary),
+            }
         }
+    }
+
+    /// Verifies the complete partial file of `output` against the hash of its
+    /// download, or else against the checksum announced by the server.
+    ///
+    /// Returns the summary to carry on with once the file may be moved into
+    /// place. A file that does not match never reaches `output`: it is
+    /// discarded along with its resume state, and the final summary is
+    /// returned as an error.
+    async fn verify(
+        &self,
+        download: &Download,
+        summary: Summary,
+        part: &Path,
+        output: &Path,
+        ctx: &Context<'_>,
+    ) -> Result<Summary, Summary> {
+        // Verify a download without a hash against the announced checksum.
+        let announced;
+        let download = match summary.expected_checksum() {
+            Some(expected) if !download.has_hash() => {
+                announced = Download {
+                    checksum: Some(expected.clone()),
+                    ..download.clone()
+                };
+                &announced
+            }
+            _ => download,
+        };
+        let summary = match (summary.checksum_source(), download.expected_checksum()) {
+            (None, Some(expected)) => {
+                summary.with_expected_checksum(ChecksumSource::Download, expected)
+            }
+            _ => summary,
+        };
+        if !download.has_hash() {
+            return Ok(summary);
+        }
+
+        ctx.events.verifying();
+
+        // Only read the file again if it was not hashed while downloaded.
+        let verified = match (summary.checksum(), download.expected_checksum()) {
+            (Some(actual), Some(expected)) if actual.algorithm == expected.algorithm => {
+                Ok(*actual == expected)
+            }
+            _ => verify_file(download, part).await,
+        };
+        match verified {
+            Ok(true) => Ok(summary),
+            Ok(false) => {
+                debug!("{:?} does not match its hash", part);
+                let message = match summary.checksum_source() {
+                    Some(ChecksumSource::Download) | None => {
+                        format!("expected {}", expected_hash(download))
+                    }
+                    Some(source) => format!("expected {} from {}", expected_hash(download), source),
+                };
+                let summary = summary.hash_mismatch(message);
+                Err(match self.discard(part, output).await {
+                    Ok(Some(destination)) => summary.with_quarantined(destination),
+                    Ok(None) => summary,
+                    Err(e) => {
+                        summary.fail(format!("Failed to discard file with wrong hash: {}", e))
+                    }
+                })
+            }
+            Err(e) => Err(summary.fail(format!("Failed to verify the hash: {}", e))),
+        }
+    }
+
+    /// Gets rid of the partial file of `output` after it failed verification,
+    /// along with its resume state.
+    ///
+    /// The file is kept where the [`MismatchPolicy`] moves mismatching files,
+    /// or next to `output` with a `.bad` suffix if the policy deletes them but
+    /// the [`VerificationPolicy`] keeps them. Returns where the file was moved
+    /// to, if it was kept.
+    async fn discard(&self, part: &Path, output: &Path) -> io::Result<Option<PathBuf>> {
+        let destination = match self.config.mismatch_policy.destination(output) {
+            None if self.config.verification == VerificationPolicy::Keep => {
+                Some(append_extension(output, BAD_SUFFIX))
+            }
+            destination => destination,
+        };
+        Journal::remove(&journal_path(output)).await;
+        match destination {
+            Some(destination) => {
+                debug!("Moving {:?} to {:?}", part, &destination);
+                move_file(part, &destination).await?;
+                Ok(Some(destination))
+            }
+            None => fs::remove_file(part).await.map(|()| None),
+        }
+    }
+
+    /// Fetches a file from its URL, failing over to its mirrors in order.
+    ///
+    /// Each mirror resumes the partial data left by the previous ones, as
+    /// long as the validators of the remote files match.
+    async fn fetch_mirrors(&self, download: &Download, ctx: &mut Context<'_>) -> Summary {
+        let urls = download.urls().collect::<Vec<_>>();
+        let mut attempt = 0;
+        loop {
+            let url = urls[attempt];
+            let summary = self.transfer(&download.with_url(url), ctx).await;
+            // Keep the name the server may have given the file.
+            let named = Download {
+                filename: summary.filename().to_string(),
+                ..download.clone()
+            };
+            let summary = summary.with_download(named).with_served_by(url.clone());
+
+            // Only failures are worth trying another mirror.
+            let reason = match summary.status() {
+                Status::Fail(e) => e.clone(),
+                Status::TimedOut(kind) => kind.to_string(),
+                _ => return summary,
+            };
+            attempt += 1;
+            let Some(next) = urls.get(attempt) else {
+                return summary;
+            };
+            debug!("Download from {} failed, trying {}: {}", url, next, reason);
+            ctx.events.retrying(
+                attempt as u32,
+                format!("failing over to {}: {}", next, reason),
+            );
+        }
+    }
+
+    /// Transfers a file from the URL of `download` and writes it to disk.
+    ///
+    /// The summary is not reported to the completion callback, as another
+    /// mirror may still be tried.
+    async fn transfer(&self, download: &Download, ctx: &mut Context<'_>) -> Summary {
+        let client = ctx.client;
+        let progress_display = ctx.progress_display;
 
         // Create a download summary.
         let mut size_on_disk: u64 = 0;
         let mut can_resume = false;
+        // Headers of the probe, describing the remote file.
+        let mut probe = HeaderMap::new();
+        // The download, named after the responses of the server if asked to.
+        let mut named = download.clone();
         let output = self.config.directory.join(&download.filename);
+        let part = part_path(&output);
+        let journal_file = journal_path(&output);
         let mut summary = Summary::new(
             download.clone(),
             StatusCode::BAD_REQUEST,
             size_on_disk,
             can_resume,
         );
-        let mut content_length: Option<u64> = None;
+        // A known size spares probing the server for it.
+        let mut content_length: Option<u64> = download.size;
+
+        // Look for the journal of a previous attempt. It can only be trusted if
+        // it describes this download and its partial file is still around.
+        let mut journal = match Journal::load(&journal_file).await {
+            Some(journal) if download.urls().any(|url| journal.matches(url)) && part.exists() => {
+                Some(journal)
+            }
+            Some(_) => {
+                Journal::remove(&journal_file).await;
+                None
+            }
+            None => None,
+        };
+
+        // Keep an existing journal up to date even if journaling was turned off.
+        let journaling = self.config.journal || journal.is_some();
 
         // If resumable is turned on...
+        ctx.events.probing();
         if self.config.resumable {
-            can_resume = match download.is_resumable(client).await {
-                Ok(r) => r,
-                Err(e) => {
-                    let summary = summary.fail(e);
-                    // Call the callback for failed downloads
-                    if let Some(ref callback) = self.config.on_complete {
-                        callback(&summary);
-                    }
-                    return summary;
-                }
+            let (resumable, res) = match download.probe(client).await {
+                Ok(probed) => probed,
+                Err(e) => return fail_with(summary, Error::from_middleware(e)),
             };
+            can_resume = resumable;
+            probe = res.headers().clone();
+            named = self.name_after(&named, &res);
+            if let Some(summary) = self.skip_named(download, &named).await {
+                return summary;
+            }
 
-            // Check if there is a file on disk already.
-            if can_resume && output.exists() {
-                debug!("A file with the same name already exists at the destination.");
+            // Check if there is a partial download on disk already.
+            if can_resume && part.exists() {
+                debug!("A partial download already exists at the destination.");
                 // If so, check file length to know where to restart the download from.
-                size_on_disk = match output.metadata() {
+                size_on_disk = match part.metadata() {
                     Ok(m) => m.len(),
-                    Err(e) => {
-                        let summary = summary.fail(e);
-                        // Call the callback for failed downloads
-                        if let Some(ref callback) = self.config.on_complete {
-                            callback(&summary);
-                        }
-                        return summary;
-                    }
+                    Err(e) => return summary.fail(e),
                 };
             }
 
@@ -303,163 +794,410 @@ impl Downloader {
         if content_length.is_none() {
             content_length = match self.get_content_length(client, download).await {
                 Ok(l) => l,
-                Err(e) => {
-                    let summary = summary.fail(e);
-                    // Call the callback for failed downloads
-                    if let Some(ref callback) = self.config.on_complete {
-                        callback(&summary);
-                    }
-                    return summary;
-                }
+                Err(e) => return fail_with(summary, Error::from_middleware(e)),
             };
         }
 
-        // Request the file.
-        debug!("Fetching {}", &download.url);
-        let mut req = client.get(download.url.as_str());
-        if self.config.resumable && can_resume {
-            req = req.header(RANGE, format!("bytes={}-", size_on_disk));
+        // A segmented download can only be resumed segment by segment, as its
+        // preallocated partial file has the size of the complete one.
+        if let Some(previous) = journal.take_if(|journal| journal.is_segmented()) {
+            if let Some(size) = content_length {
+                if can_resume && previous.total_length == Some(size) && size_on_disk == size {
+                    return self
+                        .fetch_segmented(ctx, download, &named, summary, previous)
+                        .await;
+                }
+            }
+
+            debug!("The segmented partial download cannot be resumed, discarding it.");
+            if let Err(e) = fs::remove_file(&part).await {
+                return summary.fail(e);
+            }
+            Journal::remove(&journal_file).await;
+            size_on_disk = 0;
         }
 
-        // Add extra headers if needed.
-        if let Some(ref h) = self.config.headers {
-            req = req.headers(h.to_owned());
+        // Never trust more bytes than the journal confirmed.
+        if let Some(ref previous) = journal {
+            if size_on_disk > previous.bytes_confirmed {
+                debug!(
+                    "Truncating the partial download to {} bytes.",
+                    previous.bytes_confirmed
+                );
+                if let Err(e) = truncate_file(&part, previous.bytes_confirmed).await {
+                    return summary.fail(e);
+                }
+                size_on_disk = previous.bytes_confirmed;
+            }
+        }
+
+        // Split the file across several connections when possible.
+        if let Some(size) = content_length {
+            let segments = match self.config.max_connections_per_host {
+                Some(limit) => self.config.segments.min(limit),
+                None => self.config.segments,
+            };
+            let count = segment_count(size, segments);
+            if can_resume && size_on_disk == 0 && count > 1 {
+                let segments = split_into_segments(size, count);
+                let journal = Journal::segmented(&download.url, &probe, size, &segments);
+                return self
+                    .fetch_segmented(ctx, download, &named, summary, journal)
+                    .await;
+            }
         }
 
+        // Only resume if the remote file is still the one we started with.
+        let validator = match size_on_disk {
+            0 => None,
+            _ => journal.as_ref().and_then(Journal::validator),
+        };
+
+        // Request the file.
+        debug!("Fetching {}", &download.url);
+        let req = self.file_request(client, download, size_on_disk, validator.as_ref());
+
         // Ensure there was no error while sending the request.
-        let res = match req.send().await {
+        let mut res = match req.send().await {
             Ok(res) => res,
-            Err(e) => {
-                let summary = summary.fail(e);
-                // Call the callback for failed downloads
-                if let Some(ref callback) = self.config.on_complete {
-                    callback(&summary);
-                }
-                return summary;
-            }
+            Err(e) => return fail_with(summary, Error::from_middleware(e)),
         };
 
-        // Check wether or not we need to download the file.
-        if let Some(content_length) = content_length {
-            if content_length == size_on_disk {
-                let summary = summary.with_status(Status::Skipped(
-                    "the file was already fully downloaded".into(),
-                ));
-                // Call the callback for skipped downloads
-                if let Some(ref callback) = self.config.on_complete {
-                    callback(&summary);
+        // Make sure the server answered the range request the way we expect
+        // before appending anything to the partial file.
+        let mut resume_outcome = ResumeOutcome::NotAttempted;
+        if size_on_disk > 0 {
+            match res.status() {
+                StatusCode::PARTIAL_CONTENT => {
+                    if let Err(e) = check_content_range(&res, size_on_disk) {
+                        return summary.fail(e);
+                    }
+                    resume_outcome = ResumeOutcome::Honored;
                 }
-                return summary;
+                // The partial file is either complete, or does not match the
+                // remote file at all: in that case start over.
+                StatusCode::RANGE_NOT_SATISFIABLE => {
+                    let remote_size = res
+                        .headers()
+                        .get(CONTENT_RANGE)
+                        .and_then(|h| h.to_str().ok())
+                        .and_then(parse_content_range_total)
+                        .or(content_length);
+                    if remote_size == Some(size_on_disk) {
+                        return self
+                            .complete_partial(&named, summary, &part, &output, ctx)
+                            .await;
+                    }
+
+                    debug!(
+                        "The partial file is larger than the remote file, restarting the download."
+                    );
+                    res = match self.file_request(client, download, 0, None).send().await {
+                        Ok(res) => res,
+                        Err(e) => return fail_with(summary, Error::from_middleware(e)),
+                    };
+                    size_on_disk = 0;
+                    resume_outcome = ResumeOutcome::Restarted;
+                }
+                // A full response to a range request means the partial data
+                // cannot be reused, either because the remote file changed or
+                // because the range was ignored: start over from the beginning
+                // of the response.
+                StatusCode::OK => {
+                    debug!("The range request was not honored, restarting the download.");
+                    size_on_disk = 0;
+                    resume_outcome = ResumeOutcome::Restarted;
+                }
+                _ => (),
             }
         }
+        let append = size_on_disk > 0;
 
         // Check the status for errors.
         match res.error_for_status_ref() {
             Ok(_res) => (),
-            Err(e) => {
-                let summary = summary.fail(e);
-                // Call the callback for failed downloads
-                if let Some(ref callback) = self.config.on_complete {
-                    callback(&summary);
-                }
-                return summary;
-            }
+            Err(e) => return summary.fail(e),
         };
 
+        // Name the file after the response, its partial file keeping the name
+        // the download was created with.
+        let named = self.name_after(&named, &res);
+        if let Some(summary) = self.skip_named(download, &named).await {
+            return summary;
+        }
+        let download = &named;
+
         // Update the summary with the collected details.
         let size = content_length.unwrap_or_else(|| {
             // If we still don't have content length, try to get it from the response
             get_content_length(&res)
         });
         let status = res.status();
-        summary = Summary::new(download.clone(), status, size, can_resume);
+        summary = Summary::new(download.clone(), status, size, can_resume)
+            .with_resume_outcome(resume_outcome);
 
         // If there is nothing else to download for this file, we can return.
         if size_on_disk > 0 && size == size_on_disk {
-            let summary = summary.with_status(Status::Skipped(
-                "the file was already fully downloaded".into(),
-            ));
-            // Call the callback for skipped downloads
-            if let Some(ref callback) = self.config.on_complete {
-                callback(&summary);
+            return self
+                .complete_partial(download, summary, &part, &output, ctx)
+                .await;
+        }
+
+        // Remember how many bytes the file must have once the body is written.
+        let expected_size = res.content_length().map(|len| len + size_on_disk);
+        if let (Some(expected), Some(announced)) = (download.size, expected_size) {
+            if expected != announced {
+                return summary.fail(Error::SizeMismatch {
+                    expected,
+                    announced,
+                });
             }
-            return summary;
         }
+        let expected_size = expected_size.or(download.size);
 
         // Create the progress bar.
         // If the download is being resumed, the progress bar position is
         // updated to start where the download stopped before.
         let pb = progress_display.create_child_progress(size, size_on_disk);
+        ctx.events.started(expected_size, size_on_disk);
 
         // Prepare the destination directory/file.
         let output_dir = output.parent().unwrap_or(&output);
         debug!("Creating destination directory {:?}", output_dir);
         match fs::create_dir_all(output_dir).await {
             Ok(_res) => (),
-            Err(e) => {
-                let summary = summary.fail(e);
-                // Call the callback for failed downloads
-                if let Some(ref callback) = self.config.on_complete {
-                    callback(&summary);
-                }
-                return summary;
-            }
+            Err(e) => return summary.fail(e),
         };
 
-        debug!("Creating destination file {:?}", &output);
+        // Remember the validators of a new download to safely resume it later.
+        // They are only kept in the journal, so one is written even if
+        // journaling is turned off.
+        let validated = if_range_value(res.headers()).is_some();
+        if (journaling || validated) && (journal.is_none() || !append) {
+            let mut fresh = Journal::new(&download.url, res.headers(), expected_size);
+            fresh.bytes_confirmed = size_on_disk;
+            if let Err(e) = fresh.save(&journal_file).await {
+                return summary.fail(e);
+            }
+            journal = Some(fresh);
+        }
+
+        debug!("Creating temporary file {:?}", &part);
         let mut file = match OpenOptions::new()
             .create(true)
             .write(true)
-            .append(can_resume)
-            .open(output)
+            .append(append)
+            .truncate(!append)
+            .open(&part)
             .await
         {
             Ok(file) => file,
-            Err(e) => {
-                let summary = summary.fail(e);
-                // Call the callback for failed downloads
-                if let Some(ref callback) = self.config.on_complete {
-                    callback(&summary);
-                }
-                return summary;
-            }
+            Err(e) => return summary.fail(e),
+        };
+
+        // Let the server tell what to verify the file against, unless the
+        // download already knows.
+        let announced = match self.config.checksums_from_response && !download.has_hash() {
+            true => response_checksum(res.headers()),
+            false => None,
+        };
+
+        // Hash the file as it is written, starting with the bytes already on disk.
+        let algorithm = match (download.expected_checksum(), &announced) {
+            (Some(expected), _) => Some(expected.algorithm),
+            (None, Some((_, announced))) => Some(announced.algorithm),
+            (None, None) => None,
+        };
+        let mut hasher = match algorithm {
+            Some(algorithm) => match resume_hasher(algorithm, &part, size_on_disk).await {
+                Ok(hasher) => Some(hasher),
+                Err(e) => return summary.fail(e),
+            },
+            None => None,
         };
 
         let mut final_size = size_on_disk;
+        let mut last_checkpoint = final_size;
+
+        // A connection lost mid-stream can be resumed if the server accepts
+        // ranges, as long as the remote file did not change.
+        let recoverable = can_resume
+            || status == StatusCode::PARTIAL_CONTENT
+            || res
+                .headers()
+                .get(ACCEPT_RANGES)
+                .is_some_and(|ranges| ranges != "none");
+        let recovery_validator = if_range_value(res.headers()).or(validator);
+        let mut recoveries = 0;
+        let idle_timeout = download.idle_timeout.or(self.config.idle_timeout);
+        let mut watchdog = self.config.low_speed_limit.map(Watchdog::new);
 
         // Download the file chunk by chunk.
         debug!("Retrieving chunks...");
         let mut stream = res.bytes_stream();
-        while let Some(item) = stream.next().await {
+        let mut cancelled = false;
+        loop {
+            // Stop reading while paused, and as soon as cancelled.
+            let paused = ctx.control.is_paused();
+            if !ctx.control.proceed().await {
+                cancelled = true;
+                break;
+            }
+            if let (true, Some(watchdog)) = (paused, watchdog.as_mut()) {
+                watchdog.restart();
+            }
+            let item = tokio::select! {
+                item = next_chunk(&mut stream, idle_timeout, watchdog.as_mut()) => item,
+                _ = ctx.control.cancelled() => {
+                    cancelled = true;
+                    break;
+                }
+            };
+            let Some(item) = item else {
+                break;
+            };
+
             // Retrieve chunk.
             let mut chunk = match item {
                 Ok(chunk) => chunk,
+                Err(e) if recoverable && recoveries < self.config.recovery_attempts => {
+                    recoveries += 1;
+                    summary = summary.with_recoveries(recoveries);
+                    let reason = describe(&e);
+                    ctx.events.retrying(recoveries, &reason);
+                    debug!(
+                        "Resuming {} from byte {} ({}/{}): {}",
+                        &download.url,
+                        final_size,
+                        recoveries,
+                        self.config.recovery_attempts,
+                        reason
+                    );
+
+                    // Make sure everything received so far is on disk.
+                    if let Err(e) = file.flush().await {
+                        return summary.fail(e);
+                    }
+                    if let Some(ref mut journal) = journal {
+                        if let Err(e) =
+                            checkpoint(&mut file, journal, final_size, &journal_file).await
+                        {
+                            return summary.fail(e);
+                        }
+                        last_checkpoint = final_size;
+                    }
+
+                    let res = match self
+                        .reopen(client, download, final_size, recovery_validator.as_ref())
+                        .await
+                    {
+                        Ok(res) => res,
+                        Err(e) => return fail_with(summary, e),
+                    };
+                    stream = res.bytes_stream();
+                    if let Some(ref mut watchdog) = watchdog {
+                        watchdog.restart();
+                    }
+                    continue;
+                }
                 Err(e) => {
-                    let summary = summary.fail(e);
-                    // Call the callback for failed downloads
-                    if let Some(ref callback) = self.config.on_complete {
-                        callback(&summary);
+                    // Record what was received so far before giving up.
+                    if let Some(ref mut journal) = journal {
+                        let _ = checkpoint(&mut file, journal, final_size, &journal_file).await;
                     }
-                    return summary;
+                    return fail_with(summary, e);
                 }
             };
             let chunk_size = chunk.len() as u64;
+            // Keep the chunk if paused or cancelled while throttled, the loop
+            // stops right after it is written.
+            tokio::select! {
+                _ = ctx.throttle.acquire(chunk_size) => (),
+                _ = ctx.control.stopped() => (),
+            }
             final_size += chunk_size;
+            if let Some(ref mut watchdog) = watchdog {
+                watchdog.record(chunk_size);
+            }
             pb.inc(chunk_size);
+            ctx.events.progress(final_size, expected_size);
+            if let Some(ref mut hasher) = hasher {
+                hasher.update(&chunk);
+            }
 
             // Write the chunk to disk.
             match file.write_all_buf(&mut chunk).await {
                 Ok(_res) => (),
-                Err(e) => {
-                    let summary = summary.fail(e);
-                    // Call the callback for failed downloads
-                    if let Some(ref callback) = self.config.on_complete {
-                        callback(&summary);
+                Err(e) => return summary.fail(e),
+            };
+
+            // Periodically record the progress in the journal.
+            if let Some(ref mut journal) = journal {
+                if final_size - last_checkpoint >= CHECKPOINT_INTERVAL {
+                    if let Err(e) = checkpoint(&mut file, journal, final_size, &journal_file).await
+                    {
+                        return summary.fail(e);
                     }
-                    return summary;
+                    last_checkpoint = final_size;
                 }
-            };
+            }
+        }
+
+        if let Err(e) = file.flush().await {
+            return summary.fail(e);
+        }
+
+        // Leave the partial file in a resumable state.
+        if cancelled {
+            debug!(
+                "Download of {} cancelled after {} bytes",
+                &download.url, final_size
+            );
+            if let Some(ref mut journal) = journal {
+                let _ = checkpoint(&mut file, journal, final_size, &journal_file).await;
+            }
+            return summary.cancel();
+        }
+
+        // Keep the partial file for a later resume if the body was cut short.
+        if let Some(expected_size) = expected_size {
+            if final_size != expected_size {
+                if let Some(ref mut journal) = journal {
+                    let _ = checkpoint(&mut file, journal, final_size, &journal_file).await;
+                }
+                return summary.fail(format!(
+                    "incomplete download: received {} of {} bytes",
+                    final_size, expected_size
+                ));
+            }
+        }
+
+        // Create a new summary with the real download size and success status,
+        // along with the checksum of the file.
+        let summary = Summary::new(download.clone(), status, final_size, can_resume)
+            .with_resume_outcome(resume_outcome)
+            .with_recoveries(recoveries)
+            .with_status(Status::Success);
+        let summary = match announced {
+            Some((source, checksum)) => summary.with_expected_checksum(source, checksum),
+            None => summary,
+        };
+        let summary = match hasher {
+            Some(hasher) => summary.with_checksum(hasher.finalize()),
+            None => summary,
+        };
+
+        // Only a file matching its hash is moved into place.
+        let summary = match self.verify(download, summary, &part, &output, ctx).await {
+            Ok(summary) => summary,
+            Err(summary) => return summary,
+        };
+        let destination = self.config.directory.join(&download.filename);
+        debug!("Renaming {:?} to {:?}", &part, &destination);
+        if let Err(e) = fs::rename(&part, &destination).await {
+            return summary.fail(e);
         }
+        Journal::remove(&journal_file).await;
 
         // Finish the progress bar once complete, and optionally remove it.
         progress_display.finish_child(pb);
@@ -467,26 +1205,416 @@ impl Downloader {
         // Advance the main progress bar.
         progress_display.increment_main();
 
-        // Create a new summary with the real download size and success status
-        let summary = Summary::new(download.clone(), status, final_size, can_resume)
-            .with_status(Status::Success);
+        summary
+    }
 
-        // Call the callback for successful downloads
-        if let Some(ref callback) = self.config.on_complete {
-            callback(&summary);
+    /// Names `download` after the `response` of its server, if asked to.
+    ///
+    /// The name comes from the `Content-Disposition` header, then from the
+    /// URL after redirects. The current name is kept if neither is usable.
+    fn name_after(&self, download: &Download, response: &Response) -> Download {
+        let mut named = download.clone();
+        if !self.config.filename_from_response {
+            return named;
         }
 
-        // Return the download summary.
-        summary
+        let filename = response
+            .headers()
+            .get(CONTENT_DISPOSITION)
+            .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned())
+            .and_then(|value| parse_content_disposition(&value))
+            .or_else(|| filename_from_url(response.url()));
+        if let Some(filename) = filename {
+            debug!("Saving {} as {}", &download.url, &filename);
+            named.filename = filename;
+        }
+        named
     }
 
-    /// Extract a specific file from a ZIP archive without downloading the entire ZIP.
-    async fn extract_from_zip(
+    /// Skips `download` if the file it was `named` after by its server already
+    /// exists and matches its hash, unless overwriting files.
+    async fn skip_named(&self, download: &Download, named: &Download) -> Option<Summary> {
+        let path = self.config.directory.join(&named.filename);
+        if named.filename == download.filename || self.config.overwrite || !path.exists() {
+            return None;
+        }
+
+        // A file that does not match is replaced by the download.
+        match verify_file(named, &path).await {
+            Ok(true) => {
+                let file_size = fs::metadata(&path).await.map(|m| m.len()).unwrap_or(0);
+                let summary = Summary::new(named.clone(), StatusCode::OK, file_size, false);
+                Some(summary.skip("File exists with matching hash"))
+            }
+            _ => None,
+        }
+    }
+
+    /// Fetches a file over several parallel range requests.
+    ///
+    /// The destination file is preallocated and each segment is written at its
+    /// own offset. A failed segment is retried on its own, from the last byte
+    /// it wrote, up to the configured number of retries. When `journal` comes
+    /// from an interrupted segmented download, only the missing parts of each
+    /// segment are fetched. The complete file is saved under the name of
+    /// `named`.
+    async fn fetch_segmented(
         &self,
-        client: &ClientWithMiddleware,
+        ctx: &Context<'_>,
         download: &Download,
-        progress_display: &ProgressDisplay,
+        named: &Download,
+        summary: Summary,
+        journal: Journal,
     ) -> Summary {
+        let progress_display = ctx.progress_display;
+        let output = self.config.directory.join(&download.filename);
+        let part = part_path(&output);
+        let journal_file = journal_path(&output);
+        let size = journal.total_length.unwrap_or_default();
+        let resuming = journal.bytes_confirmed > 0;
+        let persist = self.config.journal || resuming;
+        debug!(
+            "Fetching {} in {} segments",
+            &download.url,
+            journal.segments.len()
+        );
+
+        // Prepare the destination directory/file.
+        let output_dir = output.parent().unwrap_or(&output);
+        debug!("Creating destination directory {:?}", output_dir);
+        if let Err(e) = fs::create_dir_all(output_dir).await {
+            return summary.fail(e);
+        }
+
+        if !resuming {
+            debug!("Preallocating temporary file {:?}", &part);
+            let preallocated = match fs::File::create(&part).await {
+                Ok(file) => file.set_len(size).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = preallocated {
+                return summary.fail(e);
+            }
+        }
+
+        if persist {
+            if let Err(e) = journal.save(&journal_file).await {
+                return summary.fail(e);
+            }
+        }
+
+        // All the segments report to the same progress bar.
+        let pb = progress_display.create_child_progress(size, journal.bytes_confirmed);
+        ctx.events.started(Some(size), journal.bytes_confirmed);
+        let pending = journal
+            .segments
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|(_, state)| !state.is_complete())
+            .collect::<Vec<_>>();
+        let transfer = SegmentedTransfer {
+            download,
+            part: &part,
+            journal: Mutex::new(journal),
+            journal_file: persist.then_some(journal_file.as_path()),
+            pb: &pb,
+            ctx,
+            recoveries: AtomicU32::new(0),
+            announced: OnceLock::new(),
+        };
+        // Every segment fetched at the same time needs a connection, on top
+        // of the one the download was started with.
+        let extra = ctx
+            .hosts
+            .acquire_up_to(&download.url, pending.len().saturating_sub(1));
+        let concurrency = extra.count() + 1;
+        let results = stream::iter(pending)
+            .map(|(index, state)| self.fetch_segment(&transfer, index, state))
+            .buffer_unordered(concurrency)
+            .collect::<Vec<_>>()
+            .await;
+        drop(extra);
+        let recoveries = transfer.recoveries.load(Ordering::Relaxed);
+        let announced = transfer.announced.get().cloned();
+        let summary = summary.with_recoveries(recoveries);
+
+        // Report the error that matters most for the partial file.
+        let error = results
+            .into_iter()
+            .filter_map(Result::err)
+            .max_by_key(|e| match e {
+                SegmentError::Transfer(_) | SegmentError::TimedOut(_) => 0,
+                SegmentError::Cancelled => 1,
+                SegmentError::Changed => 2,
+            });
+        if let Some(e) = error {
+            match e {
+                // The data already written belongs to another version of the
+                // file, start from scratch next time.
+                SegmentError::Changed => {
+                    let _ = fs::remove_file(&part).await;
+                    Journal::remove(&journal_file).await;
+                }
+                // The journal knows which parts of the file are valid.
+                SegmentError::Transfer(_) | SegmentError::TimedOut(_) if persist => {
+                    let _ = transfer.journal.lock().await.save(&journal_file).await;
+                }
+                // A preallocated file cannot be told apart from a complete
+                // one, so it must not be left behind.
+                SegmentError::Transfer(_) | SegmentError::TimedOut(_) => {
+                    let _ = fs::remove_file(&part).await;
+                }
+                // Without its journal, a cancelled segmented download could
+                // not be resumed.
+                SegmentError::Cancelled => {
+                    let _ = transfer.journal.lock().await.save(&journal_file).await;
+                    return summary.cancel();
+                }
+            }
+            return match e {
+                SegmentError::TimedOut(kind) => summary.time_out(kind),
+                e => summary.fail(e),
+            };
+        }
+
+        let resume_outcome = match resuming {
+            true => ResumeOutcome::Honored,
+            false => ResumeOutcome::NotAttempted,
+        };
+        let summary = Summary::new(named.clone(), StatusCode::PARTIAL_CONTENT, size, true)
+            .with_resume_outcome(resume_outcome)
+            .with_recoveries(recoveries)
+            .with_status(Status::Success);
+        let summary = match announced {
+            Some((source, checksum)) => summary.with_expected_checksum(source, checksum),
+            None => summary,
+        };
+
+        // Only a file matching its hash is moved into place.
+        let summary = match self.verify(named, summary, &part, &output, ctx).await {
+            Ok(summary) => summary,
+            Err(summary) => return summary,
+        };
+        let destination = self.config.directory.join(&named.filename);
+        debug!("Renaming {:?} to {:?}", &part, &destination);
+        if let Err(e) = fs::rename(&part, &destination).await {
+            return summary.fail(e);
+        }
+        Journal::remove(&journal_file).await;
+
+        // Finish the progress bar once complete, and optionally remove it.
+        progress_display.finish_child(pb);
+
+        // Advance the main progress bar.
+        progress_display.increment_main();
+
+        summary
+    }
+
+    /// Fetches a single segment, retrying from its last written byte on failure.
+    async fn fetch_segment(
+        &self,
+        transfer: &SegmentedTransfer<'_>,
+        index: usize,
+        state: SegmentState,
+    ) -> Result<(), SegmentError> {
+        let segment = state.segment();
+        let mut written = state.written;
+        let mut attempt = 0;
+        loop {
+            match self
+                .fetch_segment_attempt(transfer, index, segment, &mut written)
+                .await
+            {
+                Ok(()) => return Ok(()),
+                Err(e @ (SegmentError::Transfer(_) | SegmentError::TimedOut(_)))
+                    if attempt < self.config.retries =>
+                {
+                    attempt += 1;
+                    transfer.recoveries.fetch_add(1, Ordering::Relaxed);
+                    transfer.ctx.events.retrying(attempt, &e);
+                    debug!(
+                        "Retrying segment {}-{} of {} ({}/{}): {}",
+                        segment.start,
+                        segment.end,
+                        &transfer.download.url,
+                        attempt,
+                        self.config.retries,
+                        e
+                    );
+                }
+                Err(SegmentError::Transfer(e)) => {
+                    return Err(SegmentError::Transfer(format!(
+                        "segment {}-{} failed: {}",
+                        segment.start, segment.end, e
+                    )))
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Performs one request for the remaining part of a segment.
+    ///
+    /// `written` is updated as chunks reach the disk so that a retry can pick
+    /// up where this attempt stopped.
+    async fn fetch_segment_attempt(
+        &self,
+        transfer: &SegmentedTransfer<'_>,
+        index: usize,
+        segment: Segment,
+        written: &mut u64,
+    ) -> Result<(), SegmentError> {
+        let mut control = transfer.ctx.control.clone();
+        if !control.proceed().await {
+            return Err(SegmentError::Cancelled);
+        }
+
+        let validator = transfer.journal.lock().await.validator();
+        let mut req = transfer
+            .ctx
+            .client
+            .get(transfer.download.url.as_str())
+            .header(RANGE, segment.range_header(*written));
+        if let Some(ref validator) = validator {
+            req = req.header(IF_RANGE, validator.clone());
+        }
+
+        // Add extra headers if needed.
+        if let Some(ref h) = self.config.headers {
+            req = req.headers(h.to_owned());
+        }
+
+        let res = req
+            .send()
+            .await
+            .map_err(|e| SegmentError::from(Error::from_middleware(e)))?;
+        match res.status() {
+            StatusCode::PARTIAL_CONTENT => (),
+            StatusCode::OK if validator.is_some() => return Err(SegmentError::Changed),
+            status => {
+                return Err(SegmentError::Transfer(format!(
+                    "expected 206 Partial Content, got {}",
+                    status
+                )))
+            }
+        }
+        check_content_range(&res, segment.start + *written).map_err(SegmentError::transfer)?;
+        // Never stitch segments of different versions of the file together,
+        // even if the server ignored the `If-Range` header.
+        {
+            let mut journal = transfer.journal.lock().await;
+            if !journal.same_version(res.headers()) {
+                return Err(SegmentError::Changed);
+            }
+            journal.set_validators(res.headers());
+        }
+        if self.config.checksums_from_response && !transfer.download.has_hash() {
+            if let Some(announced) = response_checksum(res.headers()) {
+                // All the segments describe the same file, the first one wins.
+                let _ = transfer.announced.set(announced);
+            }
+        }
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .open(transfer.part)
+            .await
+            .map_err(SegmentError::transfer)?;
+        file.seek(SeekFrom::Start(segment.start + *written))
+            .await
+            .map_err(SegmentError::transfer)?;
+
+        let mut last_checkpoint = *written;
+        let idle_timeout = transfer.download.idle_timeout.or(self.config.idle_timeout);
+        let mut watchdog = self.config.low_speed_limit.map(Watchdog::new);
+        let mut stream = res.bytes_stream();
+        let mut cancelled = false;
+        loop {
+            // Stop reading while paused, and as soon as cancelled.
+            let paused = control.is_paused();
+            if !control.proceed().await {
+                cancelled = true;
+                break;
+            }
+            if let (true, Some(watchdog)) = (paused, watchdog.as_mut()) {
+                watchdog.restart();
+            }
+            let item = tokio::select! {
+                item = next_chunk(&mut stream, idle_timeout, watchdog.as_mut()) => item,
+                _ = control.cancelled() => {
+                    cancelled = true;
+                    break;
+                }
+            };
+            let Some(item) = item else {
+                break;
+            };
+            let mut chunk = item?;
+
+            // Never write past the end of the segment.
+            let remaining = segment.len() - *written;
+            if chunk.len() as u64 > remaining {
+                chunk.truncate(remaining as usize);
+            }
+
+            let chunk_size = chunk.len() as u64;
+            // Keep the chunk if paused or cancelled while throttled, the loop
+            // stops right after it is written.
+            tokio::select! {
+                _ = transfer.ctx.throttle.acquire(chunk_size) => (),
+                _ = control.stopped() => (),
+            }
+            file.write_all_buf(&mut chunk)
+                .await
+                .map_err(SegmentError::transfer)?;
+            *written += chunk_size;
+            if let Some(ref mut watchdog) = watchdog {
+                watchdog.record(chunk_size);
+            }
+            transfer.pb.inc(chunk_size);
+            transfer
+                .ctx
+                .events
+                .progress(transfer.pb.position(), transfer.pb.length());
+
+            // Periodically record the progress in the journal.
+            if *written - last_checkpoint >= CHECKPOINT_INTERVAL {
+                file.flush().await.map_err(SegmentError::transfer)?;
+                transfer
+                    .record(index, *written)
+                    .await
+                    .map_err(SegmentError::transfer)?;
+                last_checkpoint = *written;
+            }
+
+            if *written == segment.len() {
+                break;
+            }
+        }
+        file.flush().await.map_err(SegmentError::transfer)?;
+        transfer
+            .record(index, *written)
+            .await
+            .map_err(SegmentError::transfer)?;
+
+        if cancelled {
+            return Err(SegmentError::Cancelled);
+        }
+        if *written < segment.len() {
+            return Err(SegmentError::Transfer(
+                "connection closed before the end of the segment".into(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Extract a specific file from a ZIP archive without downloading the entire ZIP.
+    async fn extract_from_zip(&self, download: &Download, ctx: &Context<'_>) -> Summary {
+        let client = ctx.client;
+        let progress_display = ctx.progress_display;
         let target_file = match download.target_file() {
             Some(file) => file,
             None => {
@@ -541,9 +1669,10 @@ impl Downloader {
             );
         }
 
-        // Write the extracted data to disk
-        debug!("Writing extracted file to {:?}", &output_path);
-        if let Err(e) = fs::write(&output_path, &extracted_data).await {
+        // Write the extracted data to a temporary file, then move it into place
+        let part = part_path(&output_path);
+        debug!("Writing extracted file to {:?}", &part);
+        if let Err(e) = fs::write(&part, &extracted_data).await {
             return self.create_error_summary(
                 download,
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -551,32 +1680,134 @@ impl Downloader {
             );
         }
 
-        // Finish the progress bar
-        progress_display.finish_child(pb);
-        progress_display.increment_main();
-
         // Create success summary
         let summary = Summary::new(download.clone(), StatusCode::OK, file_size, false)
             .with_status(Status::Success);
 
-        // Call the callback for successful downloads
-        if let Some(ref callback) = self.config.on_complete {
-            callback(&summary);
+        // The extracted file is in memory, hash it there.
+        let summary = match download.expected_checksum() {
+            Some(expected) => {
+                let mut hasher = expected.algorithm.hasher();
+                hasher.update(&extracted_data);
+                summary.with_checksum(hasher.finalize())
+            }
+            None => summary,
+        };
+
+        // Only a file matching its hash is moved into place.
+        let summary = match self
+            .verify(download, summary, &part, &output_path, ctx)
+            .await
+        {
+            Ok(summary) => summary,
+            Err(summary) => return summary,
+        };
+        debug!("Renaming {:?} to {:?}", &part, &output_path);
+        if let Err(e) = fs::rename(&part, &output_path).await {
+            return self.create_error_summary(
+                download,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to move extracted file into place: {}", e),
+            );
         }
 
+        // Finish the progress bar
+        progress_display.finish_child(pb);
+        progress_display.increment_main();
+
         summary
     }
 
-    /// Helper method to create error summaries and call callbacks.
+    /// Build the request fetching a file from `offset` onwards.
+    ///
+    /// A non-zero offset turns the request into a range request, made
+    /// conditional on `validator` when one is provided.
+    fn file_request(
+        &self,
+        client: &ClientWithMiddleware,
+        download: &Download,
+        offset: u64,
+        validator: Option<&HeaderValue>,
+    ) -> RequestBuilder {
+        let mut req = client.get(download.url.as_str());
+        if offset > 0 {
+            req = req.header(RANGE, format!("bytes={}-", offset));
+            if let Some(validator) = validator {
+                req = req.header(IF_RANGE, validator.clone());
+            }
+        }
+
+        // Add extra headers if needed.
+        if let Some(ref h) = self.config.headers {
+            req = req.headers(h.to_owned());
+        }
+
+        req
+    }
+
+    /// Request the rest of a transfer whose connection was lost at `offset`.
+    ///
+    /// Fails unless the server answers with the expected partial content.
+    async fn reopen(
+        &self,
+        client: &ClientWithMiddleware,
+        download: &Download,
+        offset: u64,
+        validator: Option<&HeaderValue>,
+    ) -> Result<Response, Error> {
+        let res = self
+            .file_request(client, download, offset, validator)
+            .send()
+            .await
+            .map_err(Error::from_middleware)?;
+        match res.status() {
+            StatusCode::PARTIAL_CONTENT => {
+                check_content_range(&res, offset)?;
+                Ok(res)
+            }
+            status => Err(Error::RangeMismatch {
+                expected: offset,
+                received: format!("status {}", status).into(),
+            }),
+        }
+    }
+
+    /// Move a fully downloaded temporary file into place, under the name of
+    /// `download`, and report it as skipped.
+    async fn complete_partial(
+        &self,
+        download: &Download,
+        summary: Summary,
+        part: &Path,
+        output: &Path,
+        ctx: &Context<'_>,
+    ) -> Summary {
+        let summary = match self.verify(download, summary, part, output, ctx).await {
+            Ok(summary) => summary,
+            Err(summary) => return summary,
+        };
+        let destination = self.config.directory.join(&download.filename);
+        debug!("Renaming {:?} to {:?}", part, destination);
+        if let Err(e) = fs::rename(part, &destination).await {
+            return summary.fail(e);
+        }
+        Journal::remove(&journal_path(output)).await;
+
+        summary.skip("the file was already fully downloaded")
+    }
+
+    /// Helper method to create error summaries.
     fn create_error_summary(
         &self,
         download: &Download,
         status_code: StatusCode,
         error_message: String,
     ) -> Summary {
-        let summary = Summary::new(download.clone(), status_code, 0, false).fail(error_message);
+        Summary::new(download.clone(), status_code, 0, false).fail(error_message)
+    }
 
-        // Call the callback for failed downloads
+    /// Helper method to call the completion callback before returning a summary.
+    fn report(&self, summary: Summary) -> Summary {
         if let Some(ref callback) = self.config.on_complete {
             callback(&summary);
         }
@@ -584,3 +1815,241 @@ impl Downloader {
         summary
     }
 }
+
+/// Start hashing a file whose first `offset` bytes are already in `part`.
+///
+/// The bytes on disk are hashed on a blocking thread.
+async fn resume_hasher(algorithm: Algorithm, part: &Path, offset: u64) -> io::Result<Hasher> {
+    let mut hasher = algorithm.hasher();
+    if offset == 0 {
+        return Ok(hasher);
+    }
+
+    let part = part.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        hasher.update_from_file(&part, offset)?;
+        Ok(hasher)
+    })
+    .await
+    .map_err(io::Error::other)?
+}
+
+/// Verify the file at `path` against the hash of `download` on a blocking thread.
+pub(crate) async fn verify_file(download: &Download, path: &Path) -> Result<bool, String> {
+    let download = download.clone();
+    let path = path.to_path_buf();
+    // Only keep the message, the error is not `Send`.
+    tokio::task::spawn_blocking(move || download.verify_hash(&path).map_err(|e| e.to_string()))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Describe the hash a download is verified against.
+pub(crate) fn expected_hash(download: &Download) -> String {
+    match (&download.checksum, &download.hash) {
+        (Some(checksum), _) => checksum.to_string(),
+        (None, Some(hash)) => hash.clone(),
+        (None, None) => String::new(),
+    }
+}
+
+/// Ensure a partial response starts at the `expected` offset.
+fn check_content_range(res: &Response, expected: u64) -> Result<(), Error> {
+    let content_range = res
+        .headers()
+        .get(CONTENT_RANGE)
+        .and_then(|h| h.to_str().ok());
+    match content_range.and_then(parse_content_range_start) {
+        Some(start) if start == expected => Ok(()),
+        _ => Err(Error::RangeMismatch {
+            expected,
+            received: content_range.unwrap_or("no Content-Range header").into(),
+        }),
+    }
+}
+
+/// What a single download borrows from the batch running it.
+pub(crate) struct Context<'a> {
+    /// HTTP client of the batch.
+    pub(crate) client: &'a ClientWithMiddleware,
+    /// Progress bars of the batch.
+    pub(crate) progress_display: &'a ProgressDisplay,
+    /// Bandwidth limits applying to the download.
+    pub(crate) throttle: Throttle<'a>,
+    /// Connections in use per host, the download already holding one.
+    pub(crate) hosts: &'a HostSlots,
+    /// Cancellation and pause state of the download.
+    pub(crate) control: Control,
+    /// Lifecycle and progress events of the download.
+    pub(crate) events: Events,
+}
+
+/// State shared by the segments of a segmented download.
+struct SegmentedTransfer<'a> {
+    /// The file being downloaded.
+    download: &'a Download,
+    /// Temporary file the segments are written to.
+    part: &'a Path,
+    /// Progress of each segment.
+    journal: Mutex<Journal>,
+    /// Where to persist the journal, if it must be persisted.
+    journal_file: Option<&'a Path>,
+    /// Progress bar shared by all the segments.
+    pb: &'a ProgressBar,
+    /// Client, bandwidth limits and control shared by all the segments.
+    ctx: &'a Context<'a>,
+    /// Number of segment requests resumed after losing their connection.
+    recoveries: AtomicU32,
+    /// Checksum of the file announced by the server.
+    announced: OnceLock<(ChecksumSource, Checksum)>,
+}
+
+impl SegmentedTransfer<'_> {
+    /// Record the progress of the segment at `index`, persisting it if needed.
+    async fn record(&self, index: usize, written: u64) -> io::Result<()> {
+        let mut journal = self.journal.lock().await;
+        journal.set_segment_written(index, written);
+        match self.journal_file {
+            Some(path) => journal.save(path).await,
+            None => Ok(()),
+        }
+    }
+}
+
+/// Reasons a segment could not be fetched.
+#[derive(Debug)]
+enum SegmentError {
+    /// The transfer failed, it can be retried from the last written byte.
+    Transfer(String),
+    /// A timeout expired, the transfer can be retried from the last written byte.
+    TimedOut(TimeoutKind),
+    /// The remote file changed since the download started.
+    Changed,
+    /// The download was cancelled.
+    Cancelled,
+}
+
+impl SegmentError {
+    /// Wrap any error as a retryable transfer error.
+    fn transfer(e: impl fmt::Display) -> Self {
+        Self::Transfer(e.to_string())
+    }
+}
+
+impl From<Error> for SegmentError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Timeout { kind } => Self::TimedOut(kind),
+            e => Self::Transfer(describe(&e)),
+        }
+    }
+}
+
+impl fmt::Display for SegmentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Transfer(e) => write!(f, "{}", e),
+            Self::TimedOut(kind) => write!(f, "{}", kind),
+            Self::Changed => write!(f, "the remote file changed since the download started"),
+            Self::Cancelled => write!(f, "the download was cancelled"),
+        }
+    }
+}
+
+/// Wait for the next chunk of `stream`.
+///
+/// Fails if no chunk arrives within `idle_timeout`, or as soon as `watchdog`
+/// finds the transfer too slow.
+async fn next_chunk<T>(
+    stream: &mut (impl Stream<Item = reqwest::Result<T>> + Unpin),
+    idle_timeout: Option<Duration>,
+    mut watchdog: Option<&mut Watchdog>,
+) -> Option<Result<T, Error>> {
+    let idle = idle_timeout.map(|timeout| Instant::now() + timeout);
+    loop {
+        if let Some(watchdog) = watchdog.as_deref_mut() {
+            if let Err(speed) = watchdog.check() {
+                let limit = watchdog.limit();
+                return Some(Err(Error::LowSpeed {
+                    speed,
+                    limit: limit.bytes_per_sec,
+                    duration: limit.duration,
+                }));
+            }
+        }
+        let check = watchdog.as_deref().map(Watchdog::deadline);
+
+        tokio::select! {
+            item = stream.next() => return item.map(|item| item.map_err(Error::from_reqwest)),
+            _ = sleep_until(idle) => {
+                return Some(Err(Error::Timeout {
+                    kind: TimeoutKind::Idle,
+                }))
+            }
+            // Check the speed again once the window is over.
+            _ = sleep_until(check) => (),
+        }
+    }
+}
+
+/// Sleep until `deadline`, or forever without one.
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => future::pending().await,
+    }
+}
+
+/// Mark `summary` as failed by `e`, reporting timeouts as such.
+fn fail_with(summary: Summary, e: Error) -> Summary {
+    match e {
+        Error::Timeout { kind } => summary.time_out(kind),
+        e => summary.fail(describe(&e)),
+    }
+}
+
+/// Describe `e`, including the message of the underlying error.
+fn describe(e: &Error) -> String {
+    match e {
+        Error::Reqwest { source } => source.to_string(),
+        Error::Internal { message, .. } => message.to_string(),
+        e => e.to_string(),
+    }
+}
+
+/// Flush the partial file and record `bytes` as confirmed in the journal.
+async fn checkpoint(
+    file: &mut fs::File,
+    journal: &mut Journal,
+    bytes: u64,
+    path: &Path,
+) -> io::Result<()> {
+    file.flush().await?;
+    journal.bytes_confirmed = bytes;
+    journal.save(path).await
+}
+
+/// Shrink the file at `path` to `len` bytes.
+async fn truncate_file(path: &Path, len: u64) -> io::Result<()> {
+    OpenOptions::new()
+        .write(true)
+        .open(path)
+        .await?
+        .set_len(len)
+        .await
+}
+
+/// Move the file at `from` to `to`, creating the directories of `to`.
+///
+/// Falls back to copying the file when it cannot be renamed, e.g. across file
+/// systems.
+pub(crate) async fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    if fs::rename(from, to).await.is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to).await?;
+    fs::remove_file(from).await
+}
@@ -0,0 +1,214 @@
+//! Live events describing the lifecycle and progress of downloads.
+//!
+//! [`Downloader::download_stream`](crate::downloader::Downloader::download_stream)
+//! runs a batch in the background and returns a stream of [`DownloadEvent`]s,
+//! allowing an application to drive its own user interface instead of the
+//! `indicatif` progress bars. Each event carries the index of its download in
+//! the batch, and every download ends with a [`DownloadEvent::Finished`] event.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use trauma::downloader::{DownloadEvent, DownloaderBuilder};
+//! use trauma::download::Download;
+//! use futures::StreamExt;
+//! use std::convert::TryFrom;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let downloads = vec![Download::try_from("https://example.com/file.zip")?];
+//! let downloader = DownloaderBuilder::hidden().build();
+//! let (_handle, events) = downloader.download_stream(downloads, None);
+//! futures::pin_mut!(events);
+//!
+//! while let Some(event) = events.next().await {
+//!     match event {
+//!         DownloadEvent::Progress { downloaded, size, .. } => {
+//!             println!("{} of {:?} bytes", downloaded, size)
+//!         }
+//!         DownloadEvent::Finished { summary, .. } => println!("{:?}", summary.status()),
+//!         _ => {}
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::download::Summary;
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Minimum delay between two [`DownloadEvent::Progress`] events of a download.
+pub const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// An event in the life of a download.
+#[derive(Debug, Clone)]
+pub enum DownloadEvent {
+    /// The download is waiting for a free slot in the batch.
+    Queued {
+        /// Index of the download in the batch.
+        index: usize,
+    },
+    /// The remote file is being probed for its size and range support.
+    Probing {
+        /// Index of the download in the batch.
+        index: usize,
+    },
+    /// The transfer started.
+    Started {
+        /// Index of the download in the batch.
+        index: usize,
+        /// Size of the complete file, if known.
+        size: Option<u64>,
+        /// Number of bytes already on disk from a previous attempt.
+        offset: u64,
+    },
+    /// More bytes were written to disk.
+    Progress {
+        /// Index of the download in the batch.
+        index: usize,
+        /// Number of bytes on disk so far, including resumed ones.
+        downloaded: u64,
+        /// Size of the complete file, if known.
+        size: Option<u64>,
+    },
+    /// A failed transfer is being retried.
+    Retrying {
+        /// Index of the download in the batch.
+        index: usize,
+        /// Number of the retry, starting at 1.
+        attempt: u32,
+        /// Why the previous attempt failed.
+        reason: String,
+    },
+    /// The hash of the file is being verified.
+    Verifying {
+        /// Index of the download in the batch.
+        index: usize,
+    },
+    /// The file is being extracted from a ZIP archive.
+    Extracting {
+        /// Index of the download in the batch.
+        index: usize,
+    },
+    /// The download is over, successfully or not.
+    Finished {
+        /// Index of the download in the batch.
+        index: usize,
+        /// Summary of the download.
+        summary: Box<Summary>,
+    },
+}
+
+impl DownloadEvent {
+    /// Get the index of the download in the batch.
+    pub fn index(&self) -> usize {
+        match self {
+            Self::Queued { index }
+            | Self::Probing { index }
+            | Self::Started { index, .. }
+            | Self::Progress { index, .. }
+            | Self::Retrying { index, .. }
+            | Self::Verifying { index }
+            | Self::Extracting { index }
+            | Self::Finished { index, .. } => *index,
+        }
+    }
+}
+
+/// Sends the events of a single download, if anyone listens to them.
+#[derive(Debug)]
+pub(crate) struct Events {
+    /// Channel to send the events to.
+    sender: Option<UnboundedSender<DownloadEvent>>,
+    /// Index of the download in the batch.
+    index: usize,
+    /// Last time a progress event was sent.
+    last_progress: Mutex<Option<Instant>>,
+}
+
+impl Events {
+    /// Create the event sender of the download at `index`.
+    pub(crate) fn new(sender: Option<UnboundedSender<DownloadEvent>>, index: usize) -> Self {
+        Self {
+            sender,
+            index,
+            last_progress: Mutex::new(None),
+        }
+    }
+
+    /// Send the event built by `event`, if anyone listens.
+    fn send(&self, event: impl FnOnce(usize) -> DownloadEvent) {
+        if let Some(ref sender) = self.sender {
+            // The receiver may be gone, the download goes on regardless.
+            let _ = sender.send(event(self.index));
+        }
+    }
+
+    /// Report that the download waits for a free slot.
+    pub(crate) fn queued(&self) {
+        self.send(|index| DownloadEvent::Queued { index });
+    }
+
+    /// Report that the remote file is being probed.
+    pub(crate) fn probing(&self) {
+        self.send(|index| DownloadEvent::Probing { index });
+    }
+
+    /// Report that the transfer started.
+    pub(crate) fn started(&self, size: Option<u64>, offset: u64) {
+        self.send(|index| DownloadEvent::Started {
+            index,
+            size,
+            offset,
+        });
+    }
+
+    /// Report progress, at most once every [`PROGRESS_INTERVAL`] unless the
+    /// download is complete.
+    pub(crate) fn progress(&self, downloaded: u64, size: Option<u64>) {
+        if self.sender.is_none() {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut last = self.last_progress.lock().unwrap_or_else(|e| e.into_inner());
+        let due = last.is_none_or(|last| now.duration_since(last) >= PROGRESS_INTERVAL);
+        if due || Some(downloaded) == size {
+            *last = Some(now);
+            self.send(|index| DownloadEvent::Progress {
+                index,
+                downloaded,
+                size,
+            });
+        }
+    }
+
+    /// Report that a failed transfer is being retried.
+    pub(crate) fn retrying(&self, attempt: u32, reason: impl ToString) {
+        self.send(|index| DownloadEvent::Retrying {
+            index,
+            attempt,
+            reason: reason.to_string(),
+        });
+    }
+
+    /// Report that the hash of the file is being verified.
+    pub(crate) fn verifying(&self) {
+        self.send(|index| DownloadEvent::Verifying { index });
+    }
+
+    /// Report that the file is being extracted from an archive.
+    pub(crate) fn extracting(&self) {
+        self.send(|index| DownloadEvent::Extracting { index });
+    }
+
+    /// Report that the download is over.
+    pub(crate) fn finished(&self, summary: &Summary) {
+        self.send(|index| DownloadEvent::Finished {
+            index,
+            summary: Box::new(summary.clone()),
+        });
+    }
+}
@@ -0,0 +1,279 @@
+//! Long-lived download manager accepting new jobs while running.
+//!
+//! Unlike [`Downloader::download`](crate::downloader::Downloader::download),
+//! which runs a fixed batch to completion, a [`DownloadManager`] runs in the
+//! background and accepts new [`Download`]s at any time with
+//! [`DownloadManager::enqueue`]. The `concurrent_downloads` setting of the
+//! downloader applies to all the jobs of the manager, and each job gets a
+//! [`JobHandle`] resolving to its [`Summary`].
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use trauma::downloader::manager::Shutdown;
+//! use trauma::downloader::DownloaderBuilder;
+//! use trauma::download::Download;
+//! use std::convert::TryFrom;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let manager = DownloaderBuilder::new()
+//!     .concurrent_downloads(4)
+//!     .build_manager();
+//!
+//! let job = manager.enqueue(Download::try_from("https://example.com/file.zip")?);
+//! let summary = job.await;
+//! println!("{:?}", summary.status());
+//!
+//! // Stop accepting jobs, and keep the ones that did not start yet.
+//! let pending = manager.shutdown(Shutdown::Persist).await;
+//! # Ok(())
+//! # }
+//! ```
+
+use super::bandwidth::{RateLimiter, Throttle};
+use super::control::DownloadHandle;
+use super::downloader::{Context, Downloader};
+use super::event::Events;
+use super::scheduler::{HostSlots, PendingQueue};
+use crate::download::{Download, Summary};
+
+use futures::stream::{FuturesUnordered, StreamExt};
+use reqwest::StatusCode;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context as TaskContext, Poll};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+/// How to stop a [`DownloadManager`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shutdown {
+    /// Run every queued job before stopping.
+    Drain,
+    /// Let the running jobs finish, and hand back the ones that did not start.
+    ///
+    /// The [`JobHandle`]s of the jobs handed back resolve to a
+    /// [`Status::Cancelled`](crate::download::Status::Cancelled) summary.
+    Persist,
+}
+
+/// A job sent to the manager.
+struct Job {
+    /// The file to download.
+    download: Download,
+    /// Controls of the job.
+    handle: DownloadHandle,
+    /// Where to send the summary of the job.
+    reply: oneshot::Sender<Summary>,
+}
+
+impl Job {
+    /// Resolve the job without running it.
+    fn cancel(self) -> Download {
+        let summary = Summary::new(self.download.clone(), StatusCode::BAD_REQUEST, 0, false);
+        let _ = self.reply.send(summary.cancel());
+        self.download
+    }
+}
+
+/// Messages sent to the manager.
+enum Command {
+    /// Queue a new job.
+    Enqueue(Box<Job>),
+    /// Stop the manager.
+    Shutdown(Shutdown),
+}
+
+/// Downloads files in the background, accepting new jobs at any time.
+///
+/// The manager must be created from within a Tokio runtime. Dropping it
+/// without calling [`DownloadManager::shutdown`] drains the queue in the
+/// background.
+#[derive(Debug)]
+pub struct DownloadManager {
+    /// Channel to the background task.
+    commands: mpsc::UnboundedSender<Command>,
+    /// The background task, returning the persisted jobs.
+    task: JoinHandle<Vec<Download>>,
+}
+
+impl DownloadManager {
+    /// Start a manager running its jobs with `downloader`.
+    pub fn new(downloader: Downloader, proxy: Option<reqwest::Proxy>) -> Self {
+        let (commands, receiver) = mpsc::unbounded_channel();
+        let task = tokio::spawn(run(downloader, proxy, receiver));
+        Self { commands, task }
+    }
+
+    /// Queue a download, returning a handle resolving to its summary.
+    pub fn enqueue(&self, download: Download) -> JobHandle {
+        let (reply, receiver) = oneshot::channel();
+        let handle = DownloadHandle::new(1);
+        let job = Job {
+            download: download.clone(),
+            handle: handle.clone(),
+            reply,
+        };
+
+        // If the manager is gone, the job resolves as cancelled.
+        if let Err(mpsc::error::SendError(Command::Enqueue(job))) =
+            self.commands.send(Command::Enqueue(Box::new(job)))
+        {
+            job.cancel();
+        }
+
+        JobHandle {
+            download,
+            handle,
+            receiver,
+        }
+    }
+
+    /// Stop accepting jobs and wait for the manager to stop.
+    ///
+    /// Returns the downloads that were not started, which is always empty
+    /// when draining the queue.
+    pub async fn shutdown(self, mode: Shutdown) -> Vec<Download> {
+        let _ = self.commands.send(Command::Shutdown(mode));
+        self.task.await.unwrap_or_default()
+    }
+}
+
+/// A job queued in a [`DownloadManager`].
+///
+/// Awaiting the handle returns the [`Summary`] of the download. The job can
+/// also be cancelled, paused and resumed through its handle.
+#[derive(Debug)]
+pub struct JobHandle {
+    /// The file being downloaded.
+    download: Download,
+    /// Controls of the job.
+    handle: DownloadHandle,
+    /// Where the summary of the job arrives.
+    receiver: oneshot::Receiver<Summary>,
+}
+
+impl JobHandle {
+    /// Get the download of the job.
+    pub fn download(&self) -> &Download {
+        &self.download
+    }
+
+    /// Cancel the job.
+    pub fn cancel(&self) {
+        self.handle.cancel();
+    }
+
+    /// Pause the job.
+    pub fn pause(&self) {
+        self.handle.pause();
+    }
+
+    /// Resume the job after a call to [`JobHandle::pause`].
+    pub fn resume(&self) {
+        self.handle.resume();
+    }
+}
+
+impl Future for JobHandle {
+    type Output = Summary;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.receiver).poll(cx).map(|summary| {
+            summary.unwrap_or_else(|_| {
+                // The manager stopped without running the job.
+                Summary::new(self.download.clone(), StatusCode::BAD_REQUEST, 0, false).cancel()
+            })
+        })
+    }
+}
+
+/// Run the jobs received on `commands` until shut down.
+async fn run(
+    downloader: Downloader,
+    proxy: Option<reqwest::Proxy>,
+    mut commands: mpsc::UnboundedReceiver<Command>,
+) -> Vec<Download> {
+    let client = downloader.http_client(proxy);
+    let progress_display = downloader.progress_display(0);
+
+    // All the jobs of the manager share the same bandwidth.
+    let bandwidth = downloader.max_bandwidth().map(RateLimiter::new);
+
+    let mut queue = PendingQueue::new(downloader.scheduling());
+    let hosts = HostSlots::new(downloader.max_connections_per_host());
+    let mut probing = FuturesUnordered::new();
+    let mut running = FuturesUnordered::new();
+    let mut shutdown = None;
+    loop {
+        // Start as many jobs as allowed.
+        while shutdown != Some(Shutdown::Persist)
+            && running.len() < downloader.concurrent_downloads()
+        {
+            let Some(job) = queue.pop(|job: &Job| hosts.available(&job.download.url)) else {
+                break;
+            };
+            let Job {
+                download,
+                handle,
+                reply,
+            } = job;
+            hosts.acquire(&download.url);
+            let mut ctx = Context {
+                client: &client,
+                progress_display: &progress_display,
+                throttle: Throttle::new(bandwidth.as_ref(), download.max_bandwidth),
+                hosts: &hosts,
+                control: handle.control(0),
+                events: Events::new(None, 0),
+            };
+            let downloader = &downloader;
+            running.push(async move {
+                let summary = downloader.fetch(&download, &mut ctx).await;
+                let _ = reply.send(summary);
+                download
+            });
+        }
+
+        // Once shut down, stop as soon as no job is left to run.
+        if shutdown.is_some() && running.is_empty() && probing.is_empty() {
+            break;
+        }
+
+        tokio::select! {
+            command = commands.recv(), if shutdown.is_none() => match command {
+                Some(Command::Enqueue(job)) => {
+                    let job = *job;
+                    progress_display.main().inc_length(1);
+                    // A size given with the download needs no request.
+                    if queue.needs_size() && job.download.size.is_none() {
+                        let (client, downloader) = (&client, &downloader);
+                        probing.push(async move {
+                            let size = downloader.get_content_length(client, &job.download).await;
+                            (job, size.ok().flatten())
+                        });
+                    } else {
+                        let (priority, size) = (job.download.priority, job.download.size);
+                        queue.push(job, priority, size);
+                    }
+                }
+                Some(Command::Shutdown(mode)) => shutdown = Some(mode),
+                // Every sender is gone, finish the queued jobs.
+                None => shutdown = Some(Shutdown::Drain),
+            },
+            Some((job, size)) = probing.next(), if !probing.is_empty() => {
+                let priority = job.download.priority;
+                queue.push(job, priority, size);
+            }
+            Some(download) = running.next(), if !running.is_empty() => {
+                hosts.release(&download.url);
+            }
+        }
+    }
+
+    drop(running);
+    progress_display.finish();
+
+    // Hand back the jobs that did not start.
+    queue.drain().into_iter().map(Job::cancel).collect()
+}
@@ -6,11 +6,21 @@
 //!
 //! # Overview
 //!
-//! The downloader module is organized into three main components:
+//! The downloader module is organized into the following components:
 //!
 //! - `downloader` - Core Downloader struct with download orchestration logic
 //! - `builder` - DownloaderBuilder for flexible configuration using the builder pattern
 //! - `config` - Configuration structures and callback types
+//! - `segment` - Byte-range splitting for segmented downloads
+//! - `bandwidth` - Token-bucket bandwidth limiting
+//! - `audit` - Verification of local files without network access
+//! - `control` - Cancellation and pause/resume handles for running batches
+//! - `event` - Lifecycle and progress events of running downloads
+//! - `manager` - Long-lived manager accepting new downloads while running
+//! - `scheduler` - Priority-aware ordering of queued downloads
+//! - `sync` - Synchronization of a directory with a manifest of downloads
+//! - `verification` - What to do with downloaded files failing verification
+//! - `watchdog` - Minimum-speed watchdog aborting slow transfers
 //!
 //! # Examples
 //!
@@ -65,10 +75,24 @@
 //! let downloader = DownloaderBuilder::hidden().build();
 //! ```
 
+pub mod audit;
+pub mod bandwidth;
 pub mod builder;
 pub mod config;
+pub mod control;
+pub mod event;
+pub mod manager;
+pub mod scheduler;
 pub mod downloader;
+pub mod segment;
+pub mod sync;
+pub mod verification;
+pub mod watchdog;
 
 pub use builder::DownloaderBuilder;
 pub use config::{DownloadCallback, HttpClientConfig};
+pub use control::DownloadHandle;
+pub use event::DownloadEvent;
+pub use manager::{DownloadManager, JobHandle};
+pub use sync::SyncReport;
 pub use downloader::Downloader;
\ No newline at end of file
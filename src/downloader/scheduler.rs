@@ -0,0 +1,218 @@
+//! Scheduling of queued downloads.
+//!
+//! Whenever a download slot frees up, the scheduler picks the pending
+//! download with the highest [`Download::priority`](crate::download::Download::priority).
+//! Downloads of equal priority are started according to the configured
+//! [`SchedulingStrategy`], and in the order they were queued otherwise.
+//!
+//! When a [connection limit per host](crate::downloader::DownloaderBuilder::max_connections_per_host)
+//! is set, downloads from a host at its limit are skipped, so that downloads
+//! from other hosts keep starting in the meantime. Every segment of a
+//! segmented download counts as a connection.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use trauma::downloader::scheduler::SchedulingStrategy;
+//! use trauma::downloader::DownloaderBuilder;
+//!
+//! // Start the smallest files first for a fast time-to-first-file.
+//! let downloader = DownloaderBuilder::new()
+//!     .scheduling(SchedulingStrategy::SmallestFirst)
+//!     .build();
+//! ```
+
+use crate::http::politeness::host_key;
+
+use reqwest::Url;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+/// How to order pending downloads of equal priority.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SchedulingStrategy {
+    /// Start downloads in the order they were queued.
+    #[default]
+    Priority,
+    /// Start the smallest downloads first.
+    ///
+    /// The size of each download is probed before it is queued, downloads of
+    /// unknown size are started last.
+    SmallestFirst,
+}
+
+/// Position of a pending item in the schedule, the greatest one goes first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Rank {
+    /// Priority of the item.
+    priority: i32,
+    /// Size of the item, smallest first, unknown sizes last.
+    size: Reverse<u64>,
+    /// Queue order of the item, oldest first.
+    sequence: Reverse<u64>,
+}
+
+/// Items waiting for a download slot.
+#[derive(Debug)]
+pub(crate) struct PendingQueue<T> {
+    /// How to order items of equal priority.
+    strategy: SchedulingStrategy,
+    /// The pending items with their rank.
+    items: Vec<(Rank, T)>,
+    /// Sequence number of the next item.
+    next: u64,
+}
+
+impl<T> PendingQueue<T> {
+    /// Create an empty queue ordering items according to `strategy`.
+    pub(crate) fn new(strategy: SchedulingStrategy) -> Self {
+        Self {
+            strategy,
+            items: Vec::new(),
+            next: 0,
+        }
+    }
+
+    /// Whether sizes must be probed before pushing items.
+    pub(crate) fn needs_size(&self) -> bool {
+        self.strategy == SchedulingStrategy::SmallestFirst
+    }
+
+    /// Queue `item` with its `priority` and, if known, its `size`.
+    pub(crate) fn push(&mut self, item: T, priority: i32, size: Option<u64>) {
+        let size = match self.strategy {
+            SchedulingStrategy::Priority => 0,
+            SchedulingStrategy::SmallestFirst => size.unwrap_or(u64::MAX),
+        };
+        let rank = Rank {
+            priority,
+            size: Reverse(size),
+            sequence: Reverse(self.next),
+        };
+        self.next += 1;
+        self.items.push((rank, item));
+    }
+
+    /// Remove the item to start next among the ones accepted by `ready`.
+    pub(crate) fn pop(&mut self, mut ready: impl FnMut(&T) -> bool) -> Option<T> {
+        let (position, _) = self
+            .items
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, item))| ready(item))
+            .max_by_key(|(_, (rank, _))| *rank)?;
+        Some(self.items.swap_remove(position).1)
+    }
+
+    /// Remove all the items, in the order they would have been started.
+    pub(crate) fn drain(&mut self) -> Vec<T> {
+        self.items.sort_by_key(|(rank, _)| Reverse(*rank));
+        self.items.drain(..).map(|(_, item)| item).collect()
+    }
+}
+
+/// Connections in use per host, against an optional limit.
+///
+/// Each running download holds one connection, and the segments of a
+/// segmented download take more with [`HostSlots::acquire_up_to`].
+#[derive(Debug)]
+pub(crate) struct HostSlots {
+    /// Maximum number of connections per host.
+    limit: Option<usize>,
+    /// Number of connections in use per host.
+    used: Mutex<HashMap<String, usize>>,
+}
+
+impl HostSlots {
+    /// Track connections allowing `limit` of them per host.
+    pub(crate) fn new(limit: Option<usize>) -> Self {
+        Self {
+            limit,
+            used: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Lock the connection counts.
+    fn used(&self) -> MutexGuard<'_, HashMap<String, usize>> {
+        self.used.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Whether a connection to the host of `url` may be opened.
+    pub(crate) fn available(&self, url: &Url) -> bool {
+        match self.limit {
+            Some(limit) => self.used().get(&host_key(url)).copied().unwrap_or(0) < limit,
+            None => true,
+        }
+    }
+
+    /// Take a connection to the host of `url`.
+    pub(crate) fn acquire(&self, url: &Url) {
+        *self.used().entry(host_key(url)).or_insert(0) += 1;
+    }
+
+    /// Take as many connections to the host of `url` as its limit allows, up
+    /// to `count`.
+    ///
+    /// The connections are given back when the returned [`ExtraSlots`] is
+    /// dropped.
+    pub(crate) fn acquire_up_to(&self, url: &Url, count: usize) -> ExtraSlots<'_> {
+        let key = host_key(url);
+        let mut used = self.used();
+        let current = used.get(&key).copied().unwrap_or(0);
+        let count = match self.limit {
+            Some(limit) => count.min(limit.saturating_sub(current)),
+            None => count,
+        };
+        if count > 0 {
+            used.insert(key.clone(), current + count);
+        }
+        ExtraSlots {
+            slots: self,
+            key,
+            count,
+        }
+    }
+
+    /// Give back a connection to the host of `url`.
+    pub(crate) fn release(&self, url: &Url) {
+        self.release_key(&host_key(url), 1);
+    }
+
+    /// Give back `count` connections to the host `key`.
+    fn release_key(&self, key: &str, count: usize) {
+        let mut used = self.used();
+        if let Some(current) = used.get_mut(key) {
+            *current = current.saturating_sub(count);
+            if *current == 0 {
+                used.remove(key);
+            }
+        }
+    }
+}
+
+/// Connections taken with [`HostSlots::acquire_up_to`], given back on drop.
+#[derive(Debug)]
+pub(crate) struct ExtraSlots<'a> {
+    /// Where the connections come from.
+    slots: &'a HostSlots,
+    /// Host of the connections.
+    key: String,
+    /// Number of connections taken.
+    count: usize,
+}
+
+impl ExtraSlots<'_> {
+    /// Number of connections taken.
+    pub(crate) fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl Drop for ExtraSlots<'_> {
+    fn drop(&mut self) {
+        if self.count > 0 {
+            self.slots.release_key(&self.key, self.count);
+        }
+    }
+}
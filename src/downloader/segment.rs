@@ -0,0 +1,89 @@
+//! Byte-range segmentation for multi-connection downloads.
+//!
+//! This module splits a file of known length into contiguous byte ranges that
+//! can be fetched in parallel with HTTP range requests and written at their
+//! respective offsets in a preallocated file.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use trauma::downloader::segment::{split_into_segments, Segment};
+//!
+//! let segments = split_into_segments(10, 3);
+//! assert_eq!(
+//!     segments,
+//!     vec![
+//!         Segment { start: 0, end: 3 },
+//!         Segment { start: 4, end: 6 },
+//!         Segment { start: 7, end: 9 },
+//!     ]
+//! );
+//! ```
+
+/// Smallest segment size worth opening a dedicated connection for (1 MiB).
+pub const MIN_SEGMENT_SIZE: u64 = 1024 * 1024;
+
+/// A contiguous byte range of a file, both bounds inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment {
+    /// Offset of the first byte of the segment.
+    pub start: u64,
+    /// Offset of the last byte of the segment.
+    pub end: u64,
+}
+
+impl Segment {
+    /// Number of bytes covered by the segment.
+    pub fn len(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    /// Return `true` if the segment does not cover any byte.
+    ///
+    /// A [`Segment`] always covers at least one byte, this method only exists
+    /// to pair with [`Segment::len`].
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Value of the `Range` header requesting the segment from `offset` on.
+    pub fn range_header(&self, offset: u64) -> String {
+        format!("bytes={}-{}", self.start + offset, self.end)
+    }
+}
+
+/// Split `length` bytes into at most `count` contiguous segments.
+///
+/// The segments are as evenly sized as possible, the first ones receiving the
+/// remainder bytes. An empty vector is returned if `length` is zero.
+pub fn split_into_segments(length: u64, count: usize) -> Vec<Segment> {
+    if length == 0 {
+        return Vec::new();
+    }
+
+    let count = (count.max(1) as u64).min(length);
+    let base = length / count;
+    let remainder = length % count;
+
+    let mut segments = Vec::with_capacity(count as usize);
+    let mut start = 0;
+    for i in 0..count {
+        let len = base + u64::from(i < remainder);
+        segments.push(Segment {
+            start,
+            end: start + len - 1,
+        });
+        start += len;
+    }
+
+    segments
+}
+
+/// Number of segments to use for a file of `length` bytes.
+///
+/// The requested `count` is reduced so that each segment is at least
+/// [`MIN_SEGMENT_SIZE`] bytes long.
+pub fn segment_count(length: u64, count: usize) -> usize {
+    let max_segments = (length / MIN_SEGMENT_SIZE).max(1);
+    (count as u64).min(max_segments) as usize
+}
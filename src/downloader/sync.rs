@@ -0,0 +1,228 @@
+//! Synchronization of a directory with a set of downloads.
+//!
+//! [`Downloader::sync`] makes a directory match a manifest, such as a list of
+//! assets published by a server along with their hashes:
+//!
+//! 1. The files already in the directory are verified in parallel against the
+//!    [checksum](crate::download::Download::checksum) or
+//!    [hash](crate::download::Download::hash) of their download, as done by
+//!    [`Downloader::audit`].
+//! 2. Only the missing files and the ones that do not match are downloaded.
+//!    Mismatching files are first deleted or moved aside according to the
+//!    [`MismatchPolicy`] of the downloader.
+//! 3. Optionally, the files that are not part of the manifest are deleted.
+//!    Mismatching files kept next to their download with a `.bad` suffix, or
+//!    quarantined in a directory within the synchronized one, are not.
+//!
+//! Existing files whose download has no hash cannot be compared, and are
+//! considered unchanged. The outcome of each file is listed in the returned
+//! [`SyncReport`].
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use trauma::downloader::DownloaderBuilder;
+//! use trauma::download::Download;
+//! use std::convert::TryFrom;
+//! use std::path::Path;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut asset = Download::try_from("https://example.com/assets/textures.pak")?;
+//! asset.checksum = Some("sha256:9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08".parse()?);
+//!
+//! let downloader = DownloaderBuilder::new().build();
+//! let report = downloader.sync(&[asset], Path::new("assets"), true, None).await;
+//! println!(
+//!     "{} added, {} updated, {} removed, {} failed",
+//!     report.added.len(),
+//!     report.updated.len(),
+//!     report.removed.len(),
+//!     report.failed.len()
+//! );
+//! # Ok(())
+//! # }
+//! ```
+
+use super::audit;
+use super::downloader::{move_file, Downloader};
+use super::verification::{MismatchPolicy, BAD_SUFFIX};
+use crate::download::journal::{self, journal_path};
+use crate::download::{Download, Status, Summary};
+use crate::utils::path::{append_extension, part_path};
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tracing::debug;
+
+/// Outcome of a [`Downloader::sync`].
+#[derive(Debug, Clone, Default)]
+pub struct SyncReport {
+    /// Files that were missing, and were downloaded.
+    pub added: Vec<Summary>,
+    /// Files that did not match their hash, and were downloaded again.
+    pub updated: Vec<Summary>,
+    /// Files that were already up to date, as [`Verified`](Status::Verified) or
+    /// [`Skipped`](Status::Skipped) when they have no hash.
+    pub unchanged: Vec<Summary>,
+    /// Files that are not part of the manifest, and were deleted.
+    pub removed: Vec<PathBuf>,
+    /// Files that could not be downloaded, or whose download did not match
+    /// its hash.
+    pub failed: Vec<Summary>,
+}
+
+impl SyncReport {
+    /// Check whether every file of the manifest is up to date.
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// Synchronizes the directory of `downloader` with `downloads`.
+///
+/// The downloader must overwrite existing files, as outdated ones are not
+/// verified twice.
+pub(crate) async fn run(
+    downloader: &Downloader,
+    downloads: &[Download],
+    prune: bool,
+    proxy: Option<reqwest::Proxy>,
+) -> SyncReport {
+    let directory = downloader.directory();
+    let mut report = SyncReport::default();
+
+    // Files that cannot be verified are downloaded again.
+    let audits = audit::run(downloads, directory).await;
+    let mut outdated = HashSet::new();
+    let mut quarantined = HashMap::new();
+    let mut pending = Vec::new();
+    for (download, summary) in downloads.iter().zip(audits) {
+        match summary.status() {
+            Status::Verified | Status::Skipped(_) => report.unchanged.push(summary),
+            Status::Missing => pending.push(download.clone()),
+            // Mismatching files are set aside first, as they are overwritten
+            // without being verified again.
+            Status::HashMismatch(_) => {
+                let path = directory.join(&download.filename);
+                let destination = downloader.mismatch_policy().destination(&path);
+                let removed = match destination {
+                    Some(ref destination) => move_file(&path, destination)
+                        .await
+                        .map_err(|e| format!("Failed to move file with wrong hash: {}", e)),
+                    None => fs::remove_file(&path)
+                        .await
+                        .map_err(|e| format!("Failed to remove file with wrong hash: {}", e)),
+                };
+                if let Err(e) = removed {
+                    report.failed.push(summary.fail(e));
+                    continue;
+                }
+
+                debug!("{:?} does not match its hash", &path);
+                if let Some(destination) = destination {
+                    quarantined.insert(download.filename.as_str(), destination);
+                }
+                outdated.insert(download.filename.as_str());
+                pending.push(download.clone());
+            }
+            _ => {
+                debug!(
+                    "{:?} is outdated: {:?}",
+                    summary.filename(),
+                    summary.status()
+                );
+                outdated.insert(download.filename.as_str());
+                pending.push(download.clone());
+            }
+        }
+    }
+
+    debug!(
+        "Syncing {:?}: {} files to download, {} up to date",
+        directory,
+        pending.len(),
+        report.unchanged.len()
+    );
+    for summary in downloader.download(&pending, proxy).await {
+        let summary = match quarantined.remove(summary.filename()) {
+            Some(destination) => summary.with_quarantined(destination),
+            None => summary,
+        };
+        match summary.status() {
+            Status::Success if outdated.contains(summary.filename()) => {
+                report.updated.push(summary)
+            }
+            Status::Success => report.added.push(summary),
+            _ => report.failed.push(summary),
+        }
+    }
+
+    if prune {
+        // Files just quarantined inside the directory are not pruned.
+        let quarantine = match downloader.mismatch_policy() {
+            MismatchPolicy::Quarantine(quarantine) => fs::canonicalize(quarantine).await.ok(),
+            _ => None,
+        };
+        report.removed = prune_directory(directory, downloads, quarantine.as_deref()).await;
+    }
+
+    report
+}
+
+/// Delete the files of `directory` that do not belong to `downloads`.
+///
+/// The partial files of the downloads, their resume state and the mismatching
+/// files kept next to them are kept, and so is the canonical `quarantine`
+/// directory. Returns the paths of the deleted files, relative to `directory`.
+async fn prune_directory(
+    directory: &Path,
+    downloads: &[Download],
+    quarantine: Option<&Path>,
+) -> Vec<PathBuf> {
+    let mut keep = HashSet::new();
+    for download in downloads {
+        let path = directory.join(&download.filename);
+        keep.insert(journal::temporary_path(&journal_path(&path)));
+        keep.insert(journal_path(&path));
+        keep.insert(append_extension(&path, BAD_SUFFIX));
+        keep.insert(part_path(&path));
+        keep.insert(path);
+    }
+
+    let mut removed = Vec::new();
+    let mut directories = vec![directory.to_path_buf()];
+    while let Some(current) = directories.pop() {
+        let mut entries = match fs::read_dir(&current).await {
+            Ok(entries) => entries,
+            Err(e) => {
+                debug!("Failed to list {:?}: {}", &current, e);
+                continue;
+            }
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            // Symbolic links are not followed.
+            match entry.file_type().await {
+                Ok(file_type) if file_type.is_dir() => {
+                    let canonical = fs::canonicalize(&path).await.ok();
+                    if quarantine.is_none() || canonical.as_deref() != quarantine {
+                        directories.push(path);
+                    }
+                }
+                Ok(_) if keep.contains(&path) => (),
+                Ok(_) => match fs::remove_file(&path).await {
+                    Ok(()) => {
+                        let relative = path.strip_prefix(directory).unwrap_or(&path);
+                        removed.push(relative.to_path_buf());
+                    }
+                    Err(e) => debug!("Failed to remove {:?}: {}", &path, e),
+                },
+                Err(e) => debug!("Failed to inspect {:?}: {}", &path, e),
+            }
+        }
+    }
+
+    removed.sort();
+    removed
+}
@@ -0,0 +1,97 @@
+//! Verification of freshly downloaded files.
+//!
+//! Once a file is downloaded or extracted, it is verified against the
+//! [`checksum`](crate::download::Download::checksum) or
+//! [`hash`](crate::download::Download::hash) of its download, if it has one,
+//! before being moved into place. A file that does not match never replaces
+//! the destination: it is reported as
+//! [`Status::HashMismatch`](crate::download::Status::HashMismatch), and the
+//! [`VerificationPolicy`] decides whether it is downloaded again or kept.
+//!
+//! Files already on disk are verified before being downloaded again. The
+//! [`MismatchPolicy`] decides what happens to the files that do not match,
+//! existing or freshly downloaded, e.g. to keep them around for
+//! investigation.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use trauma::downloader::verification::VerificationPolicy;
+//! use trauma::downloader::DownloaderBuilder;
+//!
+//! // Download corrupted files up to 2 more times before giving up.
+//! let downloader = DownloaderBuilder::new()
+//!     .verification(VerificationPolicy::Retry(2))
+//!     .build();
+//! ```
+//!
+//! ```rust
+//! use trauma::downloader::verification::MismatchPolicy;
+//! use trauma::downloader::DownloaderBuilder;
+//! use std::path::PathBuf;
+//!
+//! // Move corrupted files out of the way instead of deleting them.
+//! let downloader = DownloaderBuilder::new()
+//!     .mismatch_policy(MismatchPolicy::Quarantine(PathBuf::from("quarantine")))
+//!     .build();
+//! ```
+
+use crate::utils::path::append_extension;
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Suffix of the files kept by [`MismatchPolicy::Rename`] and
+/// [`VerificationPolicy::Keep`].
+pub const BAD_SUFFIX: &str = "bad";
+
+/// What to do with a downloaded file that does not match its hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VerificationPolicy {
+    /// Get rid of the file according to the [`MismatchPolicy`].
+    #[default]
+    Delete,
+    /// Keep the file, where the [`MismatchPolicy`] moves mismatching files,
+    /// or next to the download with a `.bad` suffix if it deletes them.
+    Keep,
+    /// Get rid of the file according to the [`MismatchPolicy`] and download
+    /// it again, up to the given number of times.
+    Retry(u32),
+}
+
+/// What to do with a file that does not match its hash, either existing
+/// before being downloaded again, or freshly downloaded.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum MismatchPolicy {
+    /// Delete the file.
+    #[default]
+    Delete,
+    /// Move the file to the given directory, under its name prefixed with the
+    /// time it was moved, in milliseconds since the UNIX epoch (e.g.
+    /// `1760695200123-file.zip`).
+    Quarantine(PathBuf),
+    /// Keep the file next to the download, with a `.bad` suffix (e.g.
+    /// `file.zip.bad`), replacing any previous one.
+    Rename,
+}
+
+impl MismatchPolicy {
+    /// Get where to move the mismatching file at `path`, or `None` if it must
+    /// be deleted.
+    pub(crate) fn destination(&self, path: &Path) -> Option<PathBuf> {
+        match self {
+            Self::Delete => None,
+            Self::Quarantine(directory) => {
+                let timestamp = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis();
+                let mut name = OsString::from(format!("{}-", timestamp));
+                name.push(path.file_name().unwrap_or_default());
+                Some(directory.join(name))
+            }
+            Self::Rename => Some(append_extension(path, BAD_SUFFIX)),
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! Minimum-speed watchdog for running transfers.
+//!
+//! Like curl's `--speed-limit` and `--speed-time`, a [`LowSpeedLimit`] aborts a
+//! transfer whose throughput stays below a number of bytes per second for a
+//! whole period. This catches servers trickling data slowly enough to defeat an
+//! idle timeout. The aborted transfer is then resumed from its last written
+//! byte when possible, see
+//! [`DownloaderBuilder::recovery_attempts`](crate::downloader::DownloaderBuilder::recovery_attempts).
+//!
+//! # Examples
+//!
+//! ```rust
+//! use trauma::downloader::DownloaderBuilder;
+//! use std::time::Duration;
+//!
+//! // Give up on connections slower than 1 KiB/s for 30 seconds.
+//! let downloader = DownloaderBuilder::new()
+//!     .low_speed_limit(1024, Duration::from_secs(30))
+//!     .build();
+//! ```
+
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Minimum throughput a transfer must sustain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LowSpeedLimit {
+    /// Minimum number of bytes per second.
+    pub bytes_per_sec: u64,
+    /// How long the throughput may stay below the minimum.
+    pub duration: Duration,
+}
+
+impl LowSpeedLimit {
+    /// Require at least `bytes_per_sec` bytes per second over every `duration`.
+    ///
+    /// A duration of zero is raised to one second.
+    pub fn new(bytes_per_sec: u64, duration: Duration) -> Self {
+        Self {
+            bytes_per_sec,
+            duration: match duration.is_zero() {
+                true => Duration::from_secs(1),
+                false => duration,
+            },
+        }
+    }
+}
+
+/// Measures the throughput of a single transfer against a [`LowSpeedLimit`].
+///
+/// The throughput is measured over consecutive windows of the limit's
+/// duration, the transfer is too slow when a whole window stays below it.
+#[derive(Debug)]
+pub(crate) struct Watchdog {
+    /// The throughput to sustain.
+    limit: LowSpeedLimit,
+    /// Start of the current window.
+    start: Instant,
+    /// Bytes received during the current window.
+    bytes: u64,
+}
+
+impl Watchdog {
+    /// Start watching a transfer.
+    pub(crate) fn new(limit: LowSpeedLimit) -> Self {
+        Self {
+            limit,
+            start: Instant::now(),
+            bytes: 0,
+        }
+    }
+
+    /// Record `bytes` received.
+    pub(crate) fn record(&mut self, bytes: u64) {
+        self.bytes += bytes;
+    }
+
+    /// Start a new window, e.g. after the transfer was paused.
+    pub(crate) fn restart(&mut self) {
+        self.start = Instant::now();
+        self.bytes = 0;
+    }
+
+    /// When the current window ends.
+    pub(crate) fn deadline(&self) -> Instant {
+        self.start + self.limit.duration
+    }
+
+    /// Check the current window once it is over.
+    ///
+    /// Returns the throughput of the window, in bytes per second, if it stayed
+    /// below the limit.
+    pub(crate) fn check(&mut self) -> Result<(), u64> {
+        let elapsed = self.start.elapsed();
+        if elapsed < self.limit.duration {
+            return Ok(());
+        }
+
+        let speed = (self.bytes as f64 / elapsed.as_secs_f64()) as u64;
+        if speed < self.limit.bytes_per_sec {
+            return Err(speed);
+        }
+        self.restart();
+        Ok(())
+    }
+
+    /// The throughput to sustain.
+    pub(crate) fn limit(&self) -> LowSpeedLimit {
+        self.limit
+    }
+}
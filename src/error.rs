@@ -4,7 +4,8 @@
 //! that can occur during download operations. All errors implement the standard
 //! Error trait and provide detailed context about failures.
 
-use std::io;
+use std::time::Duration;
+use std::{fmt, io};
 use thiserror::Error;
 
 /// Errors that can happen when using Trauma.
@@ -35,6 +36,17 @@ pub enum Error {
         cause: Box<dyn std::error::Error + Send + Sync>,
     },
 
+    /// Error from parsing a checksum.
+    ///
+    /// This variant is returned when a checksum names an unsupported algorithm,
+    /// or when its digest is malformed or has the wrong length.
+    #[error("Invalid checksum")]
+    InvalidChecksum {
+        checksum: Box<str>,
+        #[source]
+        cause: Box<dyn std::error::Error + Send + Sync>,
+    },
+
     /// I/O Error.
     ///
     /// This variant wraps standard I/O errors that can occur during file operations,
@@ -55,6 +67,59 @@ pub enum Error {
         source: reqwest::Error,
     },
 
+    /// A timeout expired.
+    ///
+    /// This variant is returned when connecting takes too long, when a transfer
+    /// receives no data for too long, or when a download misses its deadline.
+    #[error("Timed out: {kind}")]
+    Timeout { kind: TimeoutKind },
+
+    /// A transfer was too slow.
+    ///
+    /// This variant is returned when the throughput of a transfer stays below
+    /// the configured low speed limit for the whole limit's duration.
+    #[error("Transfer too slow: {speed} bytes/s over {duration:?}, below the limit of {limit} bytes/s")]
+    LowSpeed {
+        speed: u64,
+        limit: u64,
+        duration: Duration,
+    },
+
+    /// The server did not honor a range request.
+    ///
+    /// This variant is returned when a partial response does not start at the
+    /// requested offset, which would corrupt the file if its body was appended.
+    #[error("Range not honored: expected bytes from {expected}, got {received}")]
+    RangeMismatch {
+        expected: u64,
+        received: Box<str>,
+    },
+
+    /// The server announced another size than the expected one.
+    ///
+    /// This variant is returned when a download has a known size and the
+    /// response of the server, which likely serves another file, disagrees.
+    #[error("Size mismatch: expected {expected} bytes, the server announced {announced}")]
+    SizeMismatch { expected: u64, announced: u64 },
+
+    /// Invalid Metalink document.
+    ///
+    /// This variant is returned when a Metalink document is not well-formed XML
+    /// or describes a file that cannot be downloaded.
+    #[error("Invalid Metalink document: {message}")]
+    Metalink {
+        message: Box<str>,
+        #[source]
+        cause: Option<Box<dyn std::error::Error + Send + Sync>>,
+    },
+
+    /// Invalid checksum manifest.
+    ///
+    /// This variant is returned when a line of a checksum manifest is in no
+    /// known format, or holds an invalid digest.
+    #[error("Invalid checksum manifest: {message}")]
+    Manifest { message: Box<str> },
+
     /// Archive extraction error.
     ///
     /// This variant is returned when archive extraction operations fail, such as
@@ -78,6 +143,53 @@ pub enum Error {
     },
 }
 
+impl Error {
+    /// Convert a reqwest error, telling timeouts apart from other failures.
+    pub(crate) fn from_reqwest(source: reqwest::Error) -> Self {
+        match source.is_timeout() {
+            true if source.is_connect() => Self::Timeout {
+                kind: TimeoutKind::Connect,
+            },
+            true => Self::Timeout {
+                kind: TimeoutKind::Idle,
+            },
+            false => Self::Reqwest { source },
+        }
+    }
+
+    /// Convert an error of the HTTP client middleware stack.
+    pub(crate) fn from_middleware(source: reqwest_middleware::Error) -> Self {
+        match source {
+            reqwest_middleware::Error::Reqwest(e) => Self::from_reqwest(e),
+            reqwest_middleware::Error::Middleware(e) => Self::Internal {
+                message: e.to_string().into(),
+                cause: e.into(),
+            },
+        }
+    }
+}
+
+/// The timeout that expired during a download.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutKind {
+    /// The connection to the server could not be established in time.
+    Connect,
+    /// No data was received for too long.
+    Idle,
+    /// The whole download took longer than its deadline.
+    Deadline,
+}
+
+impl fmt::Display for TimeoutKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Connect => write!(f, "connection timed out"),
+            Self::Idle => write!(f, "no data received within the idle timeout"),
+            Self::Deadline => write!(f, "download deadline exceeded"),
+        }
+    }
+}
+
 /// Result type alias for operations that can fail with a Trauma error.
 ///
 /// This type alias provides a convenient way to return results from Trauma operations
@@ -10,6 +10,8 @@
 //! - **Tracing**: Request/response logging and tracing integration
 //! - **Proxy Support**: Optional HTTP/HTTPS proxy configuration
 //! - **Custom Headers**: Default headers applied to all requests
+//! - **Politeness**: Optional minimum delay between requests to the same host
+//! - **Timeouts**: Optional connect, read and total request timeouts
 //!
 //! # Examples
 //!
@@ -40,6 +42,10 @@
 //!     retries: 5,
 //!     proxy: None,
 //!     headers: Some(headers),
+//!     host_delay: None,
+//!     connect_timeout: None,
+//!     read_timeout: None,
+//!     timeout: None,
 //! };
 //!
 //! let client = create_http_client(config)?;
@@ -59,6 +65,10 @@
 //!     retries: 3,
 //!     proxy: Some(proxy),
 //!     headers: None,
+//!     host_delay: None,
+//!     connect_timeout: None,
+//!     read_timeout: None,
+//!     timeout: None,
 //! };
 //!
 //! let client = create_http_client(config)?;
@@ -66,10 +76,13 @@
 //! # }
 //! ```
 
+use super::politeness::HostDelay;
+
 use reqwest::{header::HeaderMap, Proxy};
 use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
 use reqwest_retry::{policies::ExponentialBackoff, RetryTransientMiddleware};
 use reqwest_tracing::TracingMiddleware;
+use std::time::Duration;
 
 /// Configuration for HTTP client setup.
 #[derive(Debug, Clone)]
@@ -80,6 +93,15 @@ pub struct HttpClientConfig {
     pub proxy: Option<Proxy>,
     /// Default headers to include with all requests.
     pub headers: Option<HeaderMap>,
+    /// Optional minimum delay between two requests to the same host.
+    pub host_delay: Option<Duration>,
+    /// Optional maximum time to establish a connection.
+    pub connect_timeout: Option<Duration>,
+    /// Optional maximum time to wait for each read of a response.
+    pub read_timeout: Option<Duration>,
+    /// Optional maximum duration of each request, from sending it to the end
+    /// of its body.
+    pub timeout: Option<Duration>,
 }
 
 impl Default for HttpClientConfig {
@@ -88,6 +110,10 @@ impl Default for HttpClientConfig {
             retries: 3,
             proxy: None,
             headers: None,
+            host_delay: None,
+            connect_timeout: None,
+            read_timeout: None,
+            timeout: None,
         }
     }
 }
@@ -99,6 +125,8 @@ impl Default for HttpClientConfig {
 /// - Retry middleware with exponential backoff
 /// - Optional proxy support
 /// - Optional default headers
+/// - Optional politeness delay between requests to the same host
+/// - Optional connect, read and total timeouts
 ///
 /// # Arguments
 ///
@@ -130,6 +158,17 @@ pub fn create_http_client(
         inner_client_builder = inner_client_builder.proxy(proxy);
     }
 
+    // Configure timeouts if provided
+    if let Some(timeout) = config.connect_timeout {
+        inner_client_builder = inner_client_builder.connect_timeout(timeout);
+    }
+    if let Some(timeout) = config.read_timeout {
+        inner_client_builder = inner_client_builder.read_timeout(timeout);
+    }
+    if let Some(timeout) = config.timeout {
+        inner_client_builder = inner_client_builder.timeout(timeout);
+    }
+
     // Configure default headers if provided
     if let Some(headers) = config.headers {
         inner_client_builder = inner_client_builder.default_headers(headers);
@@ -139,12 +178,18 @@ pub fn create_http_client(
     let inner_client = inner_client_builder.build()?;
 
     // Build the client with middleware
-    let client = ClientBuilder::new(inner_client)
+    let mut client_builder = ClientBuilder::new(inner_client)
         // Trace HTTP requests. See the tracing crate to make use of these traces.
         .with(TracingMiddleware::default())
         // Retry failed requests.
-        .with(RetryTransientMiddleware::new_with_policy(retry_policy))
-        .build();
+        .with(RetryTransientMiddleware::new_with_policy(retry_policy));
+
+    // Space out the requests to each host, retries included.
+    if let Some(delay) = config.host_delay {
+        client_builder = client_builder.with(HostDelay::new(delay));
+    }
+
+    let client = client_builder.build();
 
     Ok(client)
 }
@@ -6,9 +6,10 @@
 //!
 //! # Overview
 //!
-//! The HTTP module is organized into two main components:
+//! The HTTP module is organized into the following components:
 //!
 //! - [`client`] - HTTP client creation and middleware configuration
+//! - [`politeness`] - Minimum delay between requests to the same host
 //! - [`utils`] - HTTP utility functions for content length and header parsing
 //!
 //! # Examples
@@ -28,6 +29,10 @@
 //!     retries: 5,
 //!     proxy: None,
 //!     headers: Some(headers),
+//!     host_delay: None,
+//!     connect_timeout: None,
+//!     read_timeout: None,
+//!     timeout: None,
 //! };
 //!
 //! let client = create_http_client(config)?;
@@ -61,5 +66,7 @@
 //! ```
 
 pub mod client;
+pub mod politeness;
 
 pub use client::{create_http_client, HttpClientConfig};
+pub use politeness::HostDelay;
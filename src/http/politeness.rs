@@ -0,0 +1,83 @@
+//! Politeness delays between requests to the same host.
+//!
+//! [`HostDelay`] is a middleware spacing out the requests sent to each host
+//! by a minimum delay. Requests to other hosts are not affected, and
+//! concurrent requests to the same host are sent one delay apart in the
+//! order they arrived.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use trauma::http::{create_http_client, HttpClientConfig};
+//! use std::time::Duration;
+//!
+//! # fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let config = HttpClientConfig {
+//!     host_delay: Some(Duration::from_millis(500)),
+//!     ..Default::default()
+//! };
+//! let client = create_http_client(config)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use http::Extensions;
+use reqwest::{Request, Response, Url};
+use reqwest_middleware::{Middleware, Next, Result};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Middleware waiting a minimum delay between two requests to the same host.
+#[derive(Debug)]
+pub struct HostDelay {
+    /// Minimum delay between two requests to the same host.
+    delay: Duration,
+    /// Earliest time the next request to each host may be sent.
+    next: Mutex<HashMap<String, Instant>>,
+}
+
+impl HostDelay {
+    /// Create a middleware spacing the requests to each host by `delay`.
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            next: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Book the next slot of the host of `url` and return how long to wait for it.
+    pub fn reserve(&self, url: &Url) -> Duration {
+        let now = Instant::now();
+        let mut next = self.next.lock().unwrap_or_else(|e| e.into_inner());
+        let slot = next.entry(host_key(url)).or_insert(now);
+        let start = (*slot).max(now);
+        *slot = start + self.delay;
+        start - now
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware for HostDelay {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let delay = self.reserve(req.url());
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+        next.run(req, extensions).await
+    }
+}
+
+/// Identify the host of `url`, including its port.
+pub fn host_key(url: &Url) -> String {
+    match (url.host_str(), url.port_or_known_default()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_string(),
+        (None, _) => String::new(),
+    }
+}
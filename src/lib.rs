@@ -39,12 +39,13 @@ pub mod progress;
 pub mod utils;
 
 pub use download::hash::{detect_hash_type, verify_hash, HashType};
-pub use download::{Download, Status, Summary};
+pub use download::{Checksum, Download, ResumeOutcome, Status, Summary};
 pub use downloader::{Downloader, DownloaderBuilder};
-pub use error::{Error, Result};
+pub use error::{Error, Result, TimeoutKind};
 pub use http::{create_http_client, HttpClientConfig};
 pub use progress::{ProgressBarOpts, StyleOptions};
 pub use utils::content_length::{
-    extract_content_length, get_content_length, parse_content_range_total,
+    extract_content_length, get_content_length, parse_content_range_start,
+    parse_content_range_total,
 };
 pub use archive::ZipFileInfo;
@@ -77,6 +77,38 @@ pub fn parse_content_range_total(content_range: &str) -> Option<u64> {
         .and_then(|size| size.trim().parse::<u64>().ok())
 }
 
+/// Parse Content-Range header to extract the first byte position.
+///
+/// Content-Range header format: "bytes start-end/total"
+/// This function extracts the start position from the header. Unsatisfied
+/// ranges ("bytes */total") have no start position.
+///
+/// # Arguments
+///
+/// * `content_range` - The Content-Range header value as a string
+///
+/// # Returns
+///
+/// The start position as `Option<u64>`, None if parsing fails
+///
+/// # Example
+///
+/// ```rust
+/// use trauma::utils::parse_content_range_start;
+///
+/// let start = parse_content_range_start("bytes 1024-2047/2048");
+/// assert_eq!(start, Some(1024));
+/// ```
+pub fn parse_content_range_start(content_range: &str) -> Option<u64> {
+    content_range
+        .trim()
+        .strip_prefix("bytes")?
+        .trim_start()
+        .split('-')
+        .next()
+        .and_then(|start| start.trim().parse::<u64>().ok())
+}
+
 /// Extract content length from Content-Length header with fallback.
 ///
 /// This function extracts the content length from the Content-Length header,
@@ -0,0 +1,147 @@
+//! Filenames suggested by HTTP responses.
+//!
+//! Servers may name the file they send with a `Content-Disposition` header,
+//! either as a plain `filename` parameter or as an RFC 5987 encoded
+//! `filename*` parameter, which takes precedence. The last segment of the URL
+//! is used otherwise.
+//!
+//! Suggested names are reduced to their last path component, so that a
+//! malicious server cannot write outside of the download directory.
+
+use reqwest::Url;
+
+/// Extract the filename from a `Content-Disposition` header value.
+///
+/// # Example
+///
+/// ```rust
+/// use trauma::utils::parse_content_disposition;
+///
+/// let value = r#"attachment; filename="report.pdf"; filename*=UTF-8''r%C3%A9sum%C3%A9.pdf"#;
+/// assert_eq!(parse_content_disposition(value), Some("résumé.pdf".to_string()));
+/// ```
+pub fn parse_content_disposition(value: &str) -> Option<String> {
+    let mut filename = None;
+    let mut extended = None;
+    for param in split_params(value).into_iter().skip(1) {
+        let Some((name, value)) = param.split_once('=') else {
+            continue;
+        };
+        match name.trim().to_ascii_lowercase().as_str() {
+            "filename" => filename = Some(unquote(value.trim())),
+            "filename*" => extended = decode_extended(value.trim()),
+            _ => (),
+        }
+    }
+
+    extended
+        .and_then(|name| sanitize(&name))
+        .or_else(|| filename.and_then(|name| sanitize(&name)))
+}
+
+/// Get the filename from the last segment of `url`.
+///
+/// # Example
+///
+/// ```rust
+/// use reqwest::Url;
+/// use trauma::utils::filename_from_url;
+///
+/// let url = Url::parse("https://example.com/files/archive%20v2.zip?token=abc").unwrap();
+/// assert_eq!(filename_from_url(&url), Some("archive v2.zip".to_string()));
+/// ```
+pub fn filename_from_url(url: &Url) -> Option<String> {
+    let segment = url.path_segments()?.next_back()?;
+    let decoded = percent_decode(segment);
+    sanitize(&String::from_utf8_lossy(&decoded))
+}
+
+/// Split a header value on the semicolons that are not quoted.
+fn split_params(value: &str) -> Vec<&str> {
+    let mut params = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                params.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    params.push(&value[start..]);
+    params
+}
+
+/// Remove the quotes and escapes of a quoted string, if it is one.
+fn unquote(value: &str) -> String {
+    let Some(inner) = value
+        .strip_prefix('"')
+        .map(|v| v.strip_suffix('"').unwrap_or(v))
+    else {
+        return value.to_string();
+    };
+
+    let mut unquoted = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unquoted.extend(chars.next()),
+            c => unquoted.push(c),
+        }
+    }
+    unquoted
+}
+
+/// Decode an RFC 5987 value such as `UTF-8''na%C3%AFve.txt`.
+fn decode_extended(value: &str) -> Option<String> {
+    let mut parts = value.splitn(3, '\'');
+    let charset = parts.next()?;
+    let _language = parts.next()?;
+    let bytes = percent_decode(parts.next()?);
+    match charset.to_ascii_lowercase().as_str() {
+        "utf-8" => String::from_utf8(bytes).ok(),
+        // Each byte is the code point of the character.
+        "iso-8859-1" => Some(bytes.into_iter().map(char::from).collect()),
+        _ => None,
+    }
+}
+
+/// Decode the `%XX` escapes of `value`.
+fn percent_decode(value: &str) -> Vec<u8> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = bytes.get(i + 1..i + 3).filter(|_| bytes[i] == b'%');
+        match escape
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    decoded
+}
+
+/// Keep the last path component of `name`, if it is a usable filename.
+fn sanitize(name: &str) -> Option<String> {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default().trim();
+    match name {
+        "" | "." | ".." => None,
+        name if name.chars().any(char::is_control) => None,
+        name => Some(name.to_string()),
+    }
+}
@@ -9,6 +9,8 @@
 //! The utils module currently contains:
 //!
 //! - [`content_length`] - Content length extraction from HTTP responses
+//! - [`path`] - Paths of the temporary files written next to a download
+//! - [`filename`] - Filenames suggested by HTTP responses
 //!
 //! # Examples
 //!
@@ -38,6 +40,12 @@
 //! ```
 
 pub mod content_length;
+pub mod filename;
+pub mod path;
 
 // Re-export commonly used utilities
-pub use content_length::{extract_content_length, get_content_length, parse_content_range_total};
\ No newline at end of file
+pub use content_length::{
+    extract_content_length, get_content_length, parse_content_range_start, parse_content_range_total,
+};
+pub use filename::{filename_from_url, parse_content_disposition};
+pub use path::{append_extension, part_path};
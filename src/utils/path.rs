@@ -0,0 +1,45 @@
+//! Path helpers for the files written next to a download.
+//!
+//! Downloads are first written to a sibling temporary file and only moved to
+//! their final location once complete, so that an interrupted transfer never
+//! leaves a truncated file under the real name.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// Suffix of the temporary file a download is written to.
+pub const PART_SUFFIX: &str = "part";
+
+/// Append an extension to a path, keeping any existing one.
+///
+/// # Example
+///
+/// ```rust
+/// use std::path::{Path, PathBuf};
+/// use trauma::utils::append_extension;
+///
+/// assert_eq!(
+///     append_extension(Path::new("dir/file.zip"), "part"),
+///     PathBuf::from("dir/file.zip.part")
+/// );
+/// ```
+pub fn append_extension(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(".");
+    name.push(extension);
+    path.with_file_name(name)
+}
+
+/// Path of the temporary file used while downloading to `path`.
+///
+/// # Example
+///
+/// ```rust
+/// use std::path::{Path, PathBuf};
+/// use trauma::utils::part_path;
+///
+/// assert_eq!(part_path(Path::new("file.zip")), PathBuf::from("file.zip.part"));
+/// ```
+pub fn part_path(path: &Path) -> PathBuf {
+    append_extension(path, PART_SUFFIX)
+}
@@ -1,4 +1,6 @@
 use std::fs;
+use std::io::Read;
+use std::net::{TcpListener, TcpStream};
 use std::path::{Path, PathBuf};
 use tempfile::TempDir;
 use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT};
@@ -110,6 +112,10 @@ pub fn create_test_http_config() -> HttpClientConfig {
         retries: 3,
         proxy: None,
         headers: Some(create_test_headers()),
+        host_delay: None,
+        connect_timeout: None,
+        read_timeout: None,
+        timeout: None,
     }
 }
 
@@ -119,6 +125,10 @@ pub fn create_test_http_config_with_retries(retries: u32) -> HttpClientConfig {
         retries,
         proxy: None,
         headers: Some(create_test_headers()),
+        host_delay: None,
+        connect_timeout: None,
+        read_timeout: None,
+        timeout: None,
     }
 }
 
@@ -249,4 +259,39 @@ pub fn create_test_content_range_headers() -> Vec<(&'static str, Option<u64>)> {
         ("bytes 0-1023", None),
         ("", None),
     ]
-}
\ No newline at end of file
+}
+
+// === Local Server Helpers ===
+
+/// Serves HTTP on a local port, answering each request with `respond`.
+///
+/// `respond` gets the start of the raw request and writes the raw response to
+/// the connection. Returns the base URL of the server.
+pub fn spawn_test_server(respond: fn(&[u8], &mut TcpStream)) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind test server");
+    let address = listener.local_addr().expect("Failed to get test server address");
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            std::thread::spawn(move || {
+                let mut request = [0; 1024];
+                let read = stream.read(&mut request).unwrap_or(0);
+                respond(&request[..read], &mut stream);
+            });
+        }
+    });
+    format!("http://{}", address)
+}
+
+/// Deterministic content of the files served by test servers, for the given
+/// byte range.
+pub fn test_content(range: std::ops::Range<u64>) -> Vec<u8> {
+    range.map(|i| (i % 251) as u8).collect()
+}
+
+/// Parse the `Range: bytes=<start>-[<end>]` header of a raw request.
+pub fn requested_range(request: &[u8]) -> Option<(u64, Option<u64>)> {
+    let request = String::from_utf8_lossy(request).to_ascii_lowercase();
+    let range = request.split("\r\n").find_map(|line| line.strip_prefix("range: bytes="))?;
+    let (start, end) = range.trim().split_once('-')?;
+    Some((start.parse().ok()?, end.parse().ok()))
+}
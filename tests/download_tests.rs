@@ -5,8 +5,17 @@
 //! - Summary and Status functionality
 //! - Hash verification and type detection
 
-use trauma::download::Download;
-use reqwest::Url;
+use trauma::download::integrity::{response_checksum, ChecksumSource};
+use trauma::download::journal::{journal_path, Journal};
+use trauma::download::manifest::Manifest;
+use trauma::download::checksum::{Algorithm, Checksum};
+use trauma::download::metalink::parse_metalink;
+use trauma::download::resume::if_range_value;
+use trauma::download::{Download, ResumeOutcome, Status, Summary};
+use trauma::TimeoutKind;
+use reqwest::header::{HeaderMap, HeaderValue, ETAG, LAST_MODIFIED};
+use reqwest::{StatusCode, Url};
+use std::path::{Path, PathBuf};
 use std::convert::TryFrom;
 
 mod common;
@@ -37,4 +46,390 @@ fn test_try_from_custom_url() {
 mod summary_tests {
     // Placeholder for summary tests that were moved from src/download/summary.rs
     // These tests should be implemented as part of task 2
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_download_urls() {
+    let mirror = Url::parse("https://mirror.example.com/file.zip").unwrap();
+    let download = Download {
+        mirrors: vec![mirror.clone()],
+        ..Download::try_from("https://example.com/file.zip").unwrap()
+    };
+    assert_eq!(
+        download.urls().collect::<Vec<_>>(),
+        vec![&download.url, &mirror]
+    );
+}
+
+#[test]
+fn test_parse_metalink() {
+    let document = r#"<?xml version="1.0" encoding="UTF-8"?>
+<metalink xmlns="urn:ietf:params:xml:ns:metalink">
+  <published>2024-01-01T00:00:00Z</published>
+  <file name="dir/example.ext">
+    <size>14471447</size>
+    <hash type="MD5">D41D8CD98F00B204E9800998ECF8427E</hash>
+    <hash type="sha-256">E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855</hash>
+    <hash type="sha-384">38b060a751ac96384cd9327eb1b1e36a21fdb71114be0743</hash>
+    <hash type="sha-1">da39a3ee5e6b4b0d3255bfef95601890afd80709</hash>
+    <pieces length="262144" type="md5">
+      <hash>0f0a8b0f7b2ac31cc2c43b28bd9b4b27</hash>
+    </pieces>
+    <url>https://fallback.example.net/example.ext</url>
+    <url location="de" priority="2">https://mirror.example.org/example.ext</url>
+    <url priority="1">ftp://ftp.example.com/example.ext</url>
+    <url priority="1">https://example.com/example.ext?a=1&amp;b=2</url>
+    <metaurl mediatype="torrent">https://example.com/example.ext.torrent</metaurl>
+  </file>
+  <file name="other.ext">
+    <url>https://example.com/other.ext</url>
+  </file>
+</metalink>"#;
+
+    let downloads = parse_metalink(document).unwrap();
+    assert_eq!(downloads.len(), 2);
+
+    let download = &downloads[0];
+    assert_eq!(download.filename, "dir/example.ext");
+    assert_eq!(download.url.as_str(), "https://example.com/example.ext?a=1&b=2");
+    assert_eq!(
+        download.mirrors,
+        vec![
+            Url::parse("https://mirror.example.org/example.ext").unwrap(),
+            Url::parse("https://fallback.example.net/example.ext").unwrap(),
+        ]
+    );
+    assert_eq!(download.size, Some(14471447));
+    assert_eq!(
+        download.checksum.as_ref().map(ToString::to_string).as_deref(),
+        Some("sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855")
+    );
+
+    let download = &downloads[1];
+    assert_eq!(download.filename, "other.ext");
+    assert!(download.mirrors.is_empty());
+    assert_eq!(download.size, None);
+    assert_eq!(download.checksum, None);
+}
+
+#[test]
+fn test_parse_metalink_errors() {
+    let file = |name: &str, url: &str| {
+        format!(
+            r#"<metalink xmlns="urn:ietf:params:xml:ns:metalink"><file name="{}"><url>{}</url></file></metalink>"#,
+            name, url
+        )
+    };
+
+    assert!(parse_metalink(&file("../escape.ext", "https://example.com/a")).is_err());
+    assert!(parse_metalink(&file("/etc/passwd", "https://example.com/a")).is_err());
+    assert!(parse_metalink(&file("example.ext", "ftp://example.com/a")).is_err());
+    assert!(parse_metalink(&file("example.ext", "not a url")).is_err());
+    assert!(parse_metalink("<rss><file name=\"a\"/></rss>").is_err());
+    assert!(parse_metalink("<metalink><file name=\"a\">").is_err());
+    assert!(parse_metalink(&file("example.ext", "https://example.com/a")).is_ok());
+}
+
+#[test]
+fn test_parse_checksum() {
+    let checksum: Checksum = "SHA-256:9F86D081884C7D659A2FEAA0C55AD015A3BF4F1B2B0B822CD15D6C15B0F00A08"
+        .parse()
+        .unwrap();
+    assert_eq!(checksum.algorithm, Algorithm::Sha256);
+    assert_eq!(
+        checksum.to_string(),
+        "sha256:9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"
+    );
+
+    // CRC32 values may be hex or decimal.
+    let hex: Checksum = "crc32:d87f7e0c".parse().unwrap();
+    let prefixed: Checksum = "crc32:0xD87F7E0C".parse().unwrap();
+    let decimal: Checksum = "crc32:3632233996".parse().unwrap();
+    assert_eq!(hex, decimal);
+    assert_eq!(prefixed, decimal);
+    assert_eq!(hex.value, vec![0xd8, 0x7f, 0x7e, 0x0c]);
+
+    assert!("9f86d081884c7d659a2feaa0c55ad015".parse::<Checksum>().is_err());
+    assert!("sha384:9f86d081884c7d659a2feaa0c55ad015".parse::<Checksum>().is_err());
+    assert!("sha256:9f86d081884c7d659a2feaa0c55ad015".parse::<Checksum>().is_err());
+    assert!("md5:9f86d081884c7d659a2feaa0c55ad01g".parse::<Checksum>().is_err());
+    assert!("crc32:-1".parse::<Checksum>().is_err());
+}
+
+#[test]
+fn test_checksum_algorithms() {
+    let digests = [
+        (Algorithm::Md5, "098f6bcd4621d373cade4e832627b4f6"),
+        (Algorithm::Sha1, "a94a8fe5ccb19ba61c4c0873d391e987982fbbd3"),
+        (
+            Algorithm::Sha256,
+            "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08",
+        ),
+        (
+            Algorithm::Sha512,
+            "ee26b0dd4af7e749aa1a8ee3c10ae9923f618980772e473f8819a5d4940e0db2\
+             7ac185f8a0e1d5f84f88bc887fd67b143732c304cc5fa9ad8e6f57f50028a8ff",
+        ),
+        (
+            Algorithm::Blake3,
+            "4878ca0425c739fa427f7eda20fe845f6b2e46ba5fe2a14df5b1e32f50603215",
+        ),
+        (Algorithm::Xxh64, "4fdcca5ddb678139"),
+        (Algorithm::Xxh3, "9ec9f7918d7dfc40"),
+        (Algorithm::Crc32, "d87f7e0c"),
+    ];
+
+    let temp_dir = create_temp_dir();
+    let path = temp_dir.path().join("test.txt");
+    std::fs::write(&path, b"test").unwrap();
+
+    for (algorithm, digest) in digests {
+        let checksum = Checksum::from_hex(algorithm, digest).unwrap();
+        assert_eq!(Checksum::compute(algorithm, &path).unwrap(), checksum);
+        assert!(checksum.verify(&path).unwrap());
+        assert!(!checksum.verify(&temp_dir.path().join("missing")).unwrap());
+    }
+}
+
+#[test]
+fn test_download_checksum_precedence() {
+    let temp_dir = create_temp_dir();
+    let path = temp_dir.path().join("test.txt");
+    std::fs::write(&path, b"test").unwrap();
+
+    // Typed hashes are not guessed.
+    let mut download = Download::try_from("https://example.com/test.txt").unwrap();
+    download.hash = Some("crc32:d87f7e0c".to_string());
+    assert!(download.verify_hash(&path).unwrap());
+    assert_eq!(
+        Checksum::guess("3632233996"),
+        Some("crc32:d87f7e0c".parse().unwrap())
+    );
+
+    assert_eq!(
+        download.expected_checksum(),
+        Some("crc32:d87f7e0c".parse().unwrap())
+    );
+
+    // The checksum wins over the hash.
+    let checksum = Checksum::from_hex(Algorithm::Xxh64, "0000000000000000").unwrap();
+    download.checksum = Some(checksum.clone());
+    assert!(!download.verify_hash(&path).unwrap());
+    assert_eq!(download.expected_checksum(), Some(checksum));
+
+    // Untyped hashes that cannot be guessed are not silently mismatched.
+    download.checksum = None;
+    download.hash = Some("a".repeat(64));
+    assert!(download.verify_hash(&path).is_err());
+    assert_eq!(download.expected_checksum(), None);
+}
+
+#[test]
+fn test_parse_manifest() {
+    let text = "\
+# Release 1.0
+9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08  test.txt
+098f6bcd4621d373cade4e832627b4f6 *./dist/app.tar.gz
+\\9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08  back\\\\slash.txt
+SHA1 (other file.txt) = a94a8fe5ccb19ba61c4c0873d391e987982fbbd3
+
+";
+    let manifest = Manifest::parse(text, None).unwrap();
+    assert_eq!(manifest.len(), 4);
+    let names: Vec<_> = manifest.iter().map(|(name, _)| name).collect();
+    assert_eq!(
+        names,
+        ["test.txt", "dist/app.tar.gz", "back\\slash.txt", "other file.txt"]
+    );
+    assert_eq!(manifest.get("test.txt").unwrap().algorithm, Algorithm::Sha256);
+    assert_eq!(manifest.get("app.tar.gz").unwrap().algorithm, Algorithm::Md5);
+    assert_eq!(manifest.get("other file.txt").unwrap().algorithm, Algorithm::Sha1);
+    assert!(manifest.get("missing.txt").is_none());
+
+    // The given algorithm is used for GNU lines.
+    let manifest = Manifest::parse("d87f7e0c  test.txt", Some(Algorithm::Crc32)).unwrap();
+    assert_eq!(manifest.get("./test.txt"), Some(&"crc32:d87f7e0c".parse().unwrap()));
+
+    assert!(Manifest::parse("d87f7e0c  test.txt", None).is_err());
+    assert!(Manifest::parse("098f6bcd4621d373cade4e832627b4f6 test.txt", None).is_err());
+    assert!(Manifest::parse("SHA384 (test.txt) = 098f6bcd", None).is_err());
+    assert!(Manifest::parse("", None).unwrap().is_empty());
+}
+
+#[test]
+fn test_manifest_apply() {
+    let text = "\
+098f6bcd4621d373cade4e832627b4f6  a/test.txt
+098f6bcd4621d373cade4e832627b4f6  b/test.txt
+098f6bcd4621d373cade4e832627b4f6  app.tar.gz
+";
+    let manifest = Manifest::parse(text, None).unwrap();
+    let mut downloads = vec![
+        Download::try_from("https://example.com/app.tar.gz").unwrap(),
+        Download::try_from("https://example.com/test.txt").unwrap(),
+        Download::try_from("https://example.com/a/test.txt").unwrap(),
+    ];
+    downloads[2].filename = "a/test.txt".to_string();
+
+    // `test.txt` is ambiguous, since two listed files share its name.
+    assert_eq!(manifest.apply(&mut downloads), vec![1]);
+    assert!(downloads[0].checksum.is_some());
+    assert!(downloads[1].checksum.is_none());
+    assert!(downloads[2].checksum.is_some());
+}
+
+#[tokio::test]
+async fn test_manifest_from_file() {
+    let temp_dir = create_temp_dir();
+    let digest = "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262";
+    for (name, algorithm) in [
+        ("B3SUMS", Algorithm::Blake3),
+        ("SHA256SUMS", Algorithm::Sha256),
+        ("checksums.sha256", Algorithm::Sha256),
+    ] {
+        let path = temp_dir.path().join(name);
+        std::fs::write(&path, format!("{}  test.txt\n", digest)).unwrap();
+        let manifest = Manifest::from_file(&path).await.unwrap();
+        assert_eq!(manifest.get("test.txt").unwrap().algorithm, algorithm);
+    }
+}
+
+#[test]
+fn test_response_checksum() {
+    let headers = |pairs: &[(&'static str, &'static str)]| {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, HeaderValue::from_static(value));
+        }
+        headers
+    };
+    let md5 = "CY9rzUYh03PK3k6DJie09g==";
+
+    assert_eq!(response_checksum(&headers(&[])), None);
+
+    // The strongest supported digest wins.
+    let (source, checksum) = response_checksum(&headers(&[
+        ("content-md5", md5),
+        ("digest", "UNIXsum=30637, SHA=qUqP5cyxm6YcTAhz05Hph5gvu9M="),
+    ]))
+    .unwrap();
+    assert_eq!(source, ChecksumSource::Digest);
+    assert_eq!(
+        checksum.to_string(),
+        "sha1:a94a8fe5ccb19ba61c4c0873d391e987982fbbd3"
+    );
+
+    let (source, checksum) = response_checksum(&headers(&[
+        ("x-goog-hash", "crc32c=bv5r6Q=="),
+        ("x-goog-hash", "md5=CY9rzUYh03PK3k6DJie09g=="),
+    ]))
+    .unwrap();
+    assert_eq!(source, ChecksumSource::GoogHash);
+    assert_eq!(checksum.algorithm, Algorithm::Md5);
+
+    // Digests that do not describe the data received are ignored.
+    let partial = headers(&[("content-md5", md5), ("content-range", "bytes 0-1/4")]);
+    assert_eq!(response_checksum(&partial), None);
+    assert_eq!(
+        response_checksum(&headers(&[
+            ("x-goog-hash", "md5=CY9rzUYh03PK3k6DJie09g=="),
+            ("x-goog-stored-content-encoding", "gzip"),
+        ])),
+        None
+    );
+    // A SHA-1 digest is not a SHA-256 one.
+    let wrong_length = headers(&[("repr-digest", "sha-256=:qUqP5cyxm6YcTAhz05Hph5gvu9M=:")]);
+    assert_eq!(response_checksum(&wrong_length), None);
+}
+
+#[test]
+fn test_summary_resume_outcome() {
+    let summary = Summary::new(create_test_download(), StatusCode::OK, 1024, true);
+    assert_eq!(summary.resume_outcome(), ResumeOutcome::NotAttempted);
+
+    let summary = summary.with_resume_outcome(ResumeOutcome::Restarted);
+    assert_eq!(summary.resume_outcome(), ResumeOutcome::Restarted);
+}
+
+#[test]
+fn test_summary_recoveries() {
+    let summary = Summary::new(create_test_download(), StatusCode::OK, 1024, true);
+    assert_eq!(summary.recoveries(), 0);
+
+    let summary = summary.with_recoveries(2).with_status(Status::Success);
+    assert_eq!(summary.recoveries(), 2);
+    assert_eq!(summary.status(), &Status::Success);
+}
+
+#[test]
+fn test_summary_time_out() {
+    let summary = Summary::new(create_test_download(), StatusCode::OK, 1024, true);
+    let summary = summary.time_out(TimeoutKind::Connect);
+    assert_eq!(summary.status(), &Status::TimedOut(TimeoutKind::Connect));
+    assert_eq!(TimeoutKind::Connect.to_string(), "connection timed out");
+}
+
+#[test]
+fn test_if_range_value_prefers_strong_etag() {
+    let mut headers = HeaderMap::new();
+    assert!(if_range_value(&headers).is_none());
+
+    headers.insert(LAST_MODIFIED, HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"));
+    headers.insert(ETAG, HeaderValue::from_static("W/\"weak\""));
+    assert_eq!(
+        if_range_value(&headers).unwrap(),
+        "Wed, 21 Oct 2015 07:28:00 GMT"
+    );
+
+    headers.insert(ETAG, HeaderValue::from_static("\"strong\""));
+    assert_eq!(if_range_value(&headers).unwrap(), "\"strong\"");
+}
+
+#[test]
+fn test_journal_path() {
+    assert_eq!(
+        journal_path(Path::new("output/file.zip")),
+        PathBuf::from("output/file.zip.trauma.json")
+    );
+}
+
+#[test]
+fn test_journal_roundtrip() {
+    let url = Url::parse("https://example.com/file.zip").unwrap();
+    let mut headers = HeaderMap::new();
+    headers.insert(ETAG, HeaderValue::from_static("\"abc\""));
+    let mut journal = Journal::new(&url, &headers, Some(2048));
+    journal.bytes_confirmed = 1024;
+
+    let json = serde_json::to_string(&journal).unwrap();
+    let parsed: Journal = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, journal);
+    assert!(parsed.matches(&url));
+    assert!(!parsed.is_segmented());
+    assert_eq!(parsed.validator().unwrap(), "\"abc\"");
+}
+
+#[test]
+fn test_journal_segments() {
+    let url = Url::parse("https://example.com/file.zip").unwrap();
+    let segments = trauma::downloader::segment::split_into_segments(10, 2);
+    let mut journal = Journal::segmented(&url, &HeaderMap::new(), 10, &segments);
+    assert!(journal.is_segmented());
+    assert!(journal.validator().is_none());
+
+    // Responses are compared with the first validators recorded.
+    let mut headers = HeaderMap::new();
+    headers.insert(ETAG, HeaderValue::from_static("\"v1\""));
+    assert!(journal.same_version(&headers));
+    journal.set_validators(&headers);
+    assert!(journal.same_version(&headers));
+    headers.insert(ETAG, HeaderValue::from_static("\"v2\""));
+    assert!(!journal.same_version(&headers));
+
+    journal.set_segment_written(0, 5);
+    journal.set_segment_written(1, 2);
+    assert!(journal.segments[0].is_complete());
+    assert!(!journal.segments[1].is_complete());
+    assert_eq!(journal.bytes_confirmed, 7);
+}
+
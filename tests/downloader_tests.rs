@@ -5,10 +5,21 @@
 //! - DownloaderBuilder pattern
 //! - HttpClientConfig
 
-use trauma::downloader::DownloaderBuilder;
+use trauma::download::journal::{journal_path, Journal};
+use trauma::downloader::bandwidth::RateLimiter;
+use trauma::downloader::segment::{segment_count, split_into_segments, Segment, MIN_SEGMENT_SIZE};
+use trauma::downloader::manager::Shutdown;
+use trauma::downloader::scheduler::SchedulingStrategy;
+use trauma::downloader::verification::{MismatchPolicy, VerificationPolicy};
+use trauma::downloader::{DownloadEvent, DownloaderBuilder, JobHandle};
+use trauma::download::{Algorithm, Checksum, ChecksumSource, Download, ResumeOutcome, Status};
+use trauma::TimeoutKind;
 
+use futures::StreamExt;
 use reqwest::header::{HeaderValue, USER_AGENT};
+use std::io::Write;
 use std::sync::{atomic, Arc};
+use std::time::{Duration, Instant};
 
 mod common;
 use common::helpers::*;
@@ -188,4 +199,1489 @@ fn test_http_client_config_default() {
     assert_eq!(config.retries, 3);
     assert!(config.proxy.is_none());
     assert!(config.headers.is_some());
-}
\ No newline at end of file
+}
+#[test]
+fn test_builder_segments() {
+    let downloader = DownloaderBuilder::new().segments(8).build();
+    assert_eq!(downloader.segments(), 8);
+
+    // Segmentation is disabled by default, and cannot be set below one.
+    assert_eq!(DownloaderBuilder::new().build().segments(), 1);
+    assert_eq!(DownloaderBuilder::new().segments(0).build().segments(), 1);
+}
+
+#[test]
+fn test_builder_journal() {
+    assert!(!DownloaderBuilder::new().build().journal());
+    assert!(DownloaderBuilder::new().journal(true).build().journal());
+}
+
+#[test]
+fn test_builder_max_bandwidth() {
+    assert!(DownloaderBuilder::new().build().max_bandwidth().is_none());
+
+    let downloader = DownloaderBuilder::new().max_bandwidth(1024).build();
+    assert_eq!(downloader.max_bandwidth(), Some(1024));
+}
+
+#[test]
+fn test_rate_limiter_reserve() {
+    let limiter = RateLimiter::new(1000);
+    assert_eq!(limiter.rate(), 1000);
+
+    // The bucket starts full, allowing a one second burst.
+    assert_eq!(limiter.reserve(1000), Duration::ZERO);
+
+    // Going over the limit puts the limiter in debt.
+    let delay = limiter.reserve(500);
+    assert!(delay > Duration::from_millis(400) && delay <= Duration::from_millis(500));
+    let delay = limiter.reserve(500);
+    assert!(delay > Duration::from_millis(900) && delay <= Duration::from_secs(1));
+}
+
+#[test]
+fn test_download_handle_states() {
+    let downloader = DownloaderBuilder::hidden().build();
+    let downloads = vec![create_test_download()];
+    let (handle, _batch) = downloader.start(&downloads, None);
+
+    handle.pause();
+    assert!(handle.is_paused());
+    handle.resume();
+    assert!(!handle.is_paused());
+
+    // Individual downloads are addressed by their index.
+    assert!(handle.pause_download(0));
+    assert!(!handle.pause_download(1));
+
+    // A cancelled batch cannot be paused or resumed anymore.
+    handle.cancel();
+    handle.resume();
+    assert!(handle.is_cancelled());
+}
+
+#[tokio::test]
+async fn test_cancelled_batch_reports_cancelled() {
+    let downloader = DownloaderBuilder::hidden().build();
+    let downloads = create_test_downloads(2);
+    let (handle, batch) = downloader.start(&downloads, None);
+
+    handle.cancel();
+    let summaries = batch.await;
+    assert_eq!(summaries.len(), 2);
+    assert!(summaries.iter().all(|s| s.status() == &Status::Cancelled));
+}
+
+#[tokio::test]
+async fn test_download_stream_events() {
+    let downloader = DownloaderBuilder::hidden().build();
+    let (handle, events) = downloader.download_stream(create_test_downloads(2), None);
+    handle.cancel();
+
+    let events = events.collect::<Vec<_>>().await;
+    let queued = events
+        .iter()
+        .filter(|e| matches!(e, DownloadEvent::Queued { .. }))
+        .count();
+    assert_eq!(queued, 2);
+
+    // Every download ends with a single finished event.
+    let mut finished = events
+        .iter()
+        .filter_map(|e| match e {
+            DownloadEvent::Finished { index, summary } => Some((*index, summary.status())),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    finished.sort_by_key(|(index, _)| *index);
+    assert_eq!(finished, vec![(0, &Status::Cancelled), (1, &Status::Cancelled)]);
+    assert!(matches!(events.last(), Some(DownloadEvent::Finished { .. })));
+}
+
+#[tokio::test]
+async fn test_download_stream_events_in_order() {
+    let url = spawn_test_server(|request, stream| {
+        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\n");
+        if request.starts_with(b"GET") {
+            let _ = stream.write_all(b"hello");
+        }
+    });
+
+    let temp_dir = create_temp_dir();
+    let mut hasher = Algorithm::Sha256.hasher();
+    hasher.update(b"hello");
+    let mut download = Download::try_from(format!("{}/file.txt", url).as_str()).unwrap();
+    download.checksum = Some(hasher.finalize());
+    let downloader = DownloaderBuilder::hidden()
+        .directory(temp_dir.path().to_path_buf())
+        .build();
+    let (_handle, events) = downloader.download_stream(vec![download], None);
+    let events = events.collect::<Vec<_>>().await;
+
+    // Collapse the progress events, whose number depends on the chunks read.
+    let mut kinds = events
+        .iter()
+        .map(|event| match event {
+            DownloadEvent::Queued { .. } => "queued",
+            DownloadEvent::Probing { .. } => "probing",
+            DownloadEvent::Started { size, offset, .. } => {
+                assert_eq!((*size, *offset), (Some(5), 0));
+                "started"
+            }
+            DownloadEvent::Progress { .. } => "progress",
+            DownloadEvent::Retrying { .. } => "retrying",
+            DownloadEvent::Verifying { .. } => "verifying",
+            DownloadEvent::Extracting { .. } => "extracting",
+            DownloadEvent::Finished { summary, .. } => {
+                assert_eq!(summary.status(), &Status::Success);
+                "finished"
+            }
+        })
+        .collect::<Vec<_>>();
+    kinds.dedup();
+    assert_eq!(
+        kinds,
+        vec!["queued", "probing", "started", "progress", "verifying", "finished"]
+    );
+
+    let downloaded = events.iter().rev().find_map(|event| match event {
+        DownloadEvent::Progress { downloaded, size, .. } => Some((*downloaded, *size)),
+        _ => None,
+    });
+    assert_eq!(downloaded, Some((5, Some(5))));
+}
+
+#[tokio::test]
+async fn test_manager_drains_cancelled_jobs() {
+    let manager = DownloaderBuilder::hidden().build_manager();
+    let jobs = create_test_downloads(3)
+        .into_iter()
+        .map(|download| manager.enqueue(download))
+        .collect::<Vec<_>>();
+    jobs.iter().for_each(JobHandle::cancel);
+
+    let pending = manager.shutdown(Shutdown::Drain).await;
+    assert!(pending.is_empty());
+    for job in jobs {
+        assert_eq!(job.await.status(), &Status::Cancelled);
+    }
+}
+
+#[tokio::test]
+async fn test_manager_runs_jobs_enqueued_while_running() {
+    static GETS: atomic::AtomicUsize = atomic::AtomicUsize::new(0);
+    let url = spawn_test_server(|request, stream| {
+        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 4\r\n\r\n");
+        if request.starts_with(b"GET") {
+            GETS.fetch_add(1, atomic::Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(100));
+            let _ = stream.write_all(b"data");
+        }
+    });
+
+    let temp_dir = create_temp_dir();
+    let manager = DownloaderBuilder::hidden()
+        .directory(temp_dir.path().to_path_buf())
+        .build_manager();
+    let first = manager.enqueue(Download::try_from(format!("{}/a.bin", url).as_str()).unwrap());
+    while GETS.load(atomic::Ordering::SeqCst) == 0 {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    let second = manager.enqueue(Download::try_from(format!("{}/b.bin", url).as_str()).unwrap());
+
+    assert_eq!(first.await.status(), &Status::Success);
+    assert_eq!(second.await.status(), &Status::Success);
+    assert!(manager.shutdown(Shutdown::Drain).await.is_empty());
+    assert_eq!(std::fs::read(temp_dir.path().join("b.bin")).unwrap(), b"data");
+}
+
+#[tokio::test]
+async fn test_manager_limits_concurrent_downloads() {
+    static OPEN: atomic::AtomicUsize = atomic::AtomicUsize::new(0);
+    static PEAK: atomic::AtomicUsize = atomic::AtomicUsize::new(0);
+    let url = spawn_test_server(|request, stream| {
+        if request.starts_with(b"GET") {
+            let open = OPEN.fetch_add(1, atomic::Ordering::SeqCst) + 1;
+            PEAK.fetch_max(open, atomic::Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(100));
+            OPEN.fetch_sub(1, atomic::Ordering::SeqCst);
+        }
+        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 4\r\n\r\n");
+        if request.starts_with(b"GET") {
+            let _ = stream.write_all(b"data");
+        }
+    });
+
+    let temp_dir = create_temp_dir();
+    let manager = DownloaderBuilder::hidden()
+        .directory(temp_dir.path().to_path_buf())
+        .concurrent_downloads(2)
+        .build_manager();
+    let enqueue = |name: &str| {
+        manager.enqueue(Download::try_from(format!("{}/{}", url, name).as_str()).unwrap())
+    };
+
+    // The jobs enqueued later wait for the running ones to free a slot.
+    let mut jobs = vec![enqueue("a.bin"), enqueue("b.bin")];
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    jobs.extend(["c.bin", "d.bin", "e.bin"].map(enqueue));
+
+    for job in jobs {
+        assert_eq!(job.await.status(), &Status::Success);
+    }
+    manager.shutdown(Shutdown::Drain).await;
+    assert_eq!(PEAK.load(atomic::Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_manager_persist_returns_pending_jobs() {
+    let url = spawn_test_server(|request, stream| {
+        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 4\r\n\r\n");
+        if request.starts_with(b"GET") {
+            std::thread::sleep(Duration::from_millis(100));
+            let _ = stream.write_all(b"data");
+        }
+    });
+
+    let temp_dir = create_temp_dir();
+    let manager = DownloaderBuilder::hidden()
+        .directory(temp_dir.path().to_path_buf())
+        .concurrent_downloads(1)
+        .build_manager();
+    let jobs = ["a.bin", "b.bin", "c.bin"]
+        .map(|name| {
+            manager.enqueue(Download::try_from(format!("{}/{}", url, name).as_str()).unwrap())
+        })
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    // The running job finishes, the other ones are handed back.
+    let mut pending = manager
+        .shutdown(Shutdown::Persist)
+        .await
+        .into_iter()
+        .map(|download| download.filename)
+        .collect::<Vec<_>>();
+    pending.sort();
+    assert_eq!(pending, vec!["b.bin", "c.bin"]);
+
+    let statuses = futures::future::join_all(jobs).await;
+    let statuses = statuses.iter().map(|s| s.status()).collect::<Vec<_>>();
+    assert_eq!(statuses, vec![&Status::Success, &Status::Cancelled, &Status::Cancelled]);
+    assert!(!temp_dir.path().join("b.bin").exists());
+}
+
+#[tokio::test]
+async fn test_manager_reuses_known_sizes() {
+    static HEADS: atomic::AtomicUsize = atomic::AtomicUsize::new(0);
+    let url = spawn_test_server(|request, stream| {
+        if request.starts_with(b"HEAD") {
+            HEADS.fetch_add(1, atomic::Ordering::SeqCst);
+        }
+        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 4\r\n\r\n");
+        if request.starts_with(b"GET") {
+            let _ = stream.write_all(b"data");
+        }
+    });
+
+    let temp_dir = create_temp_dir();
+    let manager = DownloaderBuilder::hidden()
+        .directory(temp_dir.path().to_path_buf())
+        .scheduling(SchedulingStrategy::SmallestFirst)
+        .build_manager();
+    let jobs = ["a.bin", "b.bin"]
+        .map(|name| {
+            let mut download = Download::try_from(format!("{}/{}", url, name).as_str()).unwrap();
+            download.size = Some(4);
+            manager.enqueue(download)
+        })
+        .into_iter()
+        .collect::<Vec<_>>();
+    for job in jobs {
+        assert_eq!(job.await.status(), &Status::Success);
+    }
+    manager.shutdown(Shutdown::Drain).await;
+
+    // Only the resume probe of each download reached the server.
+    assert_eq!(HEADS.load(atomic::Ordering::SeqCst), 2);
+}
+
+#[test]
+fn test_builder_scheduling() {
+    let downloader = DownloaderBuilder::new().build();
+    assert_eq!(downloader.scheduling(), SchedulingStrategy::Priority);
+
+    let downloader = DownloaderBuilder::new()
+        .scheduling(SchedulingStrategy::SmallestFirst)
+        .build();
+    assert_eq!(downloader.scheduling(), SchedulingStrategy::SmallestFirst);
+}
+
+#[test]
+fn test_builder_host_limits() {
+    let downloader = DownloaderBuilder::new().build();
+    assert!(downloader.max_connections_per_host().is_none());
+    assert!(downloader.host_delay().is_none());
+
+    let downloader = DownloaderBuilder::new()
+        .max_connections_per_host(0)
+        .host_delay(Duration::from_millis(250))
+        .build();
+    assert_eq!(downloader.max_connections_per_host(), Some(1));
+    assert_eq!(downloader.host_delay(), Some(Duration::from_millis(250)));
+}
+
+#[test]
+fn test_builder_recovery_attempts() {
+    assert_eq!(DownloaderBuilder::new().build().recovery_attempts(), 3);
+
+    let downloader = DownloaderBuilder::new().recovery_attempts(0).build();
+    assert_eq!(downloader.recovery_attempts(), 0);
+}
+
+#[test]
+fn test_builder_timeouts() {
+    let downloader = DownloaderBuilder::new().build();
+    assert!(downloader.connect_timeout().is_none());
+    assert!(downloader.idle_timeout().is_none());
+    assert!(downloader.deadline().is_none());
+
+    let downloader = DownloaderBuilder::new()
+        .connect_timeout(Duration::from_secs(5))
+        .idle_timeout(Duration::from_secs(10))
+        .deadline(Duration::from_secs(60))
+        .build();
+    assert_eq!(downloader.connect_timeout(), Some(Duration::from_secs(5)));
+    assert_eq!(downloader.idle_timeout(), Some(Duration::from_secs(10)));
+    assert_eq!(downloader.deadline(), Some(Duration::from_secs(60)));
+}
+
+#[tokio::test]
+async fn test_stalled_download_times_out() {
+    // A server announcing 100 bytes but only ever sending 10 of them.
+    let url = spawn_test_server(|request, stream| {
+        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 100\r\n\r\n");
+        if request.starts_with(b"GET") {
+            let _ = stream.write_all(&[0; 10]);
+            std::thread::sleep(Duration::from_secs(5));
+        }
+    });
+
+    let temp_dir = create_temp_dir();
+    let download = create_test_download_with_filename(&format!("{}/stall", url), "stall");
+    let downloader = DownloaderBuilder::hidden()
+        .directory(temp_dir.path().to_path_buf())
+        .recovery_attempts(0)
+        .idle_timeout(Duration::from_millis(200))
+        .build();
+    let summaries = downloader.download(std::slice::from_ref(&download), None).await;
+    assert_eq!(summaries[0].status(), &Status::TimedOut(TimeoutKind::Idle));
+
+    // The deadline of the download wins over a longer idle timeout.
+    let download = Download {
+        idle_timeout: Some(Duration::from_secs(10)),
+        deadline: Some(Duration::from_millis(300)),
+        ..download
+    };
+    let summaries = downloader.download(&[download], None).await;
+    assert_eq!(summaries[0].status(), &Status::TimedOut(TimeoutKind::Deadline));
+}
+
+#[tokio::test]
+async fn test_lost_connection_is_recovered() {
+    static RESUMED_FROM: atomic::AtomicU64 = atomic::AtomicU64::new(0);
+    // A server closing the connection halfway through the body, once.
+    let url = spawn_test_server(|request, stream| {
+        let size = 65536;
+        match requested_range(request) {
+            Some((start, _)) => {
+                RESUMED_FROM.store(start, atomic::Ordering::SeqCst);
+                let head = format!(
+                    "HTTP/1.1 206 Partial Content\r\ncontent-length: {}\r\n\
+                     content-range: bytes {}-{}/{}\r\n\r\n",
+                    size - start,
+                    start,
+                    size - 1,
+                    size
+                );
+                let _ = stream.write_all(head.as_bytes());
+                let _ = stream.write_all(&test_content(start..size));
+            }
+            None => {
+                let _ = stream.write_all(
+                    b"HTTP/1.1 200 OK\r\naccept-ranges: bytes\r\ncontent-length: 65536\r\n\r\n",
+                );
+                if request.starts_with(b"GET") {
+                    let _ = stream.write_all(&test_content(0..size / 2));
+                }
+            }
+        }
+    });
+
+    let temp_dir = create_temp_dir();
+    let download = create_test_download_with_filename(&format!("{}/lost", url), "lost");
+    let downloader = DownloaderBuilder::hidden()
+        .directory(temp_dir.path().to_path_buf())
+        .build();
+    let summaries = downloader.download(&[download], None).await;
+
+    // Only the missing half is requested again.
+    assert_eq!(summaries[0].status(), &Status::Success);
+    assert_eq!(summaries[0].recoveries(), 1);
+    assert_eq!(RESUMED_FROM.load(atomic::Ordering::SeqCst), 32768);
+    let content = std::fs::read(temp_dir.path().join("lost")).unwrap();
+    assert!(content == test_content(0..65536));
+}
+
+#[test]
+fn test_builder_low_speed_limit() {
+    assert!(DownloaderBuilder::new().build().low_speed_limit().is_none());
+
+    let downloader = DownloaderBuilder::new()
+        .low_speed_limit(1024, Duration::ZERO)
+        .build();
+    let limit = downloader.low_speed_limit().unwrap();
+    assert_eq!(limit.bytes_per_sec, 1024);
+    assert_eq!(limit.duration, Duration::from_secs(1));
+}
+
+#[tokio::test]
+async fn test_slow_download_is_aborted() {
+    // A server trickling its body one byte at a time.
+    let url = spawn_test_server(|request, stream| {
+        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 100\r\n\r\n");
+        if request.starts_with(b"GET") {
+            for _ in 0..100 {
+                let _ = stream.write_all(&[0]);
+                std::thread::sleep(Duration::from_millis(50));
+            }
+        }
+    });
+
+    let temp_dir = create_temp_dir();
+    let download = create_test_download_with_filename(&format!("{}/slow", url), "slow");
+    let downloader = DownloaderBuilder::hidden()
+        .directory(temp_dir.path().to_path_buf())
+        .recovery_attempts(0)
+        .low_speed_limit(1000, Duration::from_millis(300))
+        .build();
+    let summaries = downloader.download(&[download], None).await;
+    match summaries[0].status() {
+        Status::Fail(reason) => assert!(reason.contains("too slow"), "{}", reason),
+        status => panic!("unexpected status {:?}", status),
+    }
+}
+
+#[tokio::test]
+async fn test_filename_from_response() {
+    static HEADS: atomic::AtomicUsize = atomic::AtomicUsize::new(0);
+    let url = spawn_test_server(|request, stream| {
+        if request.starts_with(b"HEAD") {
+            HEADS.fetch_add(1, atomic::Ordering::SeqCst);
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\n");
+            return;
+        }
+        // Only the file request names the file.
+        let _ = stream.write_all(
+            b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\n\
+              content-disposition: attachment; filename=\"report.txt\"\r\n\r\nhello",
+        );
+    });
+
+    let temp_dir = create_temp_dir();
+    let mut download = Download::try_from(format!("{}/download?id=123", url).as_str()).unwrap();
+    assert_eq!(download.filename, "download");
+    download.size = Some(5);
+
+    let downloader = DownloaderBuilder::hidden()
+        .directory(temp_dir.path().to_path_buf())
+        .filename_from_response(true)
+        .build();
+    assert!(downloader.filename_from_response());
+    let summaries = downloader.download(std::slice::from_ref(&download), None).await;
+    assert_eq!(summaries[0].status(), &Status::Success);
+    assert_eq!(summaries[0].filename(), "report.txt");
+    assert_eq!(std::fs::read(temp_dir.path().join("report.txt")).unwrap(), b"hello");
+    assert!(!temp_dir.path().join("download").exists());
+    assert!(!temp_dir.path().join("download.part").exists());
+
+    // No request is sent just to name the file.
+    assert_eq!(HEADS.load(atomic::Ordering::SeqCst), 1);
+
+    // The file is not downloaded again under its new name.
+    std::fs::write(temp_dir.path().join("report.txt"), b"kept").unwrap();
+    let summaries = downloader.download(&[download], None).await;
+    assert!(matches!(summaries[0].status(), Status::Skipped(_)));
+    assert_eq!(summaries[0].filename(), "report.txt");
+    assert_eq!(std::fs::read(temp_dir.path().join("report.txt")).unwrap(), b"kept");
+}
+
+#[tokio::test]
+async fn test_mirror_failover() {
+    let primary = spawn_test_server(|_, stream| {
+        let _ = stream.write_all(b"HTTP/1.1 500 Internal Server Error\r\ncontent-length: 0\r\n\r\n");
+    });
+    let mirror = spawn_test_server(|request, stream| {
+        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\n");
+        if request.starts_with(b"GET") {
+            let _ = stream.write_all(b"hello");
+        }
+    });
+
+    let temp_dir = create_temp_dir();
+    let mirror_url = reqwest::Url::parse(&format!("{}/file.txt", mirror)).unwrap();
+    let download = Download {
+        mirrors: vec![mirror_url.clone()],
+        ..Download::try_from(format!("{}/file.txt", primary).as_str()).unwrap()
+    };
+
+    let downloader = DownloaderBuilder::hidden()
+        .directory(temp_dir.path().to_path_buf())
+        .retries(0)
+        .build();
+    let summaries = downloader.download(std::slice::from_ref(&download), None).await;
+    assert_eq!(summaries[0].status(), &Status::Success);
+    assert_eq!(summaries[0].served_by(), Some(&mirror_url));
+    assert_eq!(summaries[0].download().url, download.url);
+    assert_eq!(std::fs::read(temp_dir.path().join("file.txt")).unwrap(), b"hello");
+}
+
+#[tokio::test]
+async fn test_size_mismatch_fails() {
+    let url = spawn_test_server(|request, stream| {
+        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\n");
+        if request.starts_with(b"GET") {
+            let _ = stream.write_all(b"hello");
+        }
+    });
+
+    let temp_dir = create_temp_dir();
+    let download = Download {
+        size: Some(4),
+        ..Download::try_from(format!("{}/file.txt", url).as_str()).unwrap()
+    };
+
+    let downloader = DownloaderBuilder::hidden()
+        .directory(temp_dir.path().to_path_buf())
+        .build();
+    let summaries = downloader.download(&[download], None).await;
+    assert!(matches!(summaries[0].status(), Status::Fail(e) if e.contains("Size mismatch")));
+    assert!(!temp_dir.path().join("file.txt").exists());
+}
+
+#[tokio::test]
+async fn test_unrecognized_hash_fails() {
+    static REQUESTS: atomic::AtomicUsize = atomic::AtomicUsize::new(0);
+    let url = spawn_test_server(|request, stream| {
+        REQUESTS.fetch_add(1, atomic::Ordering::SeqCst);
+        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\n");
+        if request.starts_with(b"GET") {
+            let _ = stream.write_all(b"hello");
+        }
+    });
+
+    // An untyped SHA-256 digest is neither an MD5 nor a CRC32.
+    let temp_dir = create_temp_dir();
+    let download = Download {
+        hash: Some("2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824".into()),
+        ..Download::try_from(format!("{}/file.txt", url).as_str()).unwrap()
+    };
+    let downloader = DownloaderBuilder::hidden()
+        .directory(temp_dir.path().to_path_buf())
+        .verification(VerificationPolicy::Retry(2))
+        .build();
+    let summaries = downloader.download(&[download], None).await;
+
+    assert_eq!(
+        summaries[0].status(),
+        &Status::Fail("unrecognized hash format".into())
+    );
+    assert_eq!(REQUESTS.load(atomic::Ordering::SeqCst), 0);
+    assert!(!temp_dir.path().join("file.txt").exists());
+    assert!(!temp_dir.path().join("file.txt.part").exists());
+}
+
+#[tokio::test]
+async fn test_downloaded_file_is_verified() {
+    static GETS: atomic::AtomicUsize = atomic::AtomicUsize::new(0);
+    let url = spawn_test_server(|request, stream| {
+        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\n");
+        if request.starts_with(b"GET") {
+            GETS.fetch_add(1, atomic::Ordering::SeqCst);
+            let _ = stream.write_all(b"hello");
+        }
+    });
+
+    let temp_dir = create_temp_dir();
+    let path = temp_dir.path().join("file.txt");
+    let mut hasher = Algorithm::Sha256.hasher();
+    hasher.update(b"hello");
+    let mut download = Download::try_from(format!("{}/file.txt", url).as_str()).unwrap();
+    download.checksum = Some(hasher.finalize());
+    let wrong = Download {
+        checksum: Some(Checksum::from_hex(Algorithm::Crc32, "00000000").unwrap()),
+        ..download.clone()
+    };
+    let builder = || {
+        DownloaderBuilder::hidden()
+            .directory(temp_dir.path().to_path_buf())
+            .overwrite(true)
+    };
+
+    let summaries = builder().build().download(std::slice::from_ref(&download), None).await;
+    assert_eq!(summaries[0].status(), &Status::Success);
+    assert_eq!(summaries[0].checksum(), download.checksum.as_ref());
+    assert!(path.exists());
+
+    // A download that does not match never replaces the existing file.
+    assert_eq!(builder().build().verification(), VerificationPolicy::Delete);
+    std::fs::write(&path, b"old").unwrap();
+    let summaries = builder().build().download(std::slice::from_ref(&wrong), None).await;
+    assert!(matches!(summaries[0].status(), Status::HashMismatch(_)));
+    assert_eq!(summaries[0].quarantined(), None);
+    assert_eq!(std::fs::read(&path).unwrap(), b"old");
+    assert!(!temp_dir.path().join("file.txt.part").exists());
+    std::fs::remove_file(&path).unwrap();
+
+    // Kept files are set aside rather than moved into place.
+    let downloader = builder().verification(VerificationPolicy::Keep).build();
+    let summaries = downloader.download(std::slice::from_ref(&wrong), None).await;
+    let bad = temp_dir.path().join("file.txt.bad");
+    assert!(matches!(summaries[0].status(), Status::HashMismatch(_)));
+    assert_eq!(summaries[0].quarantined(), Some(bad.as_path()));
+    assert_eq!(std::fs::read(&bad).unwrap(), b"hello");
+    assert!(!path.exists());
+
+    // Retried downloads are only reported once.
+    let reported = Arc::new(atomic::AtomicUsize::new(0));
+    let counter = reported.clone();
+    let downloader = builder()
+        .verification(VerificationPolicy::Retry(2))
+        .on_complete(move |_| {
+            counter.fetch_add(1, atomic::Ordering::SeqCst);
+        })
+        .build();
+    GETS.store(0, atomic::Ordering::SeqCst);
+    let summaries = downloader.download(&[wrong], None).await;
+    assert!(matches!(summaries[0].status(), Status::HashMismatch(_)));
+    assert_eq!(GETS.load(atomic::Ordering::SeqCst), 3);
+    assert_eq!(reported.load(atomic::Ordering::SeqCst), 1);
+    assert!(!path.exists());
+}
+
+#[tokio::test]
+async fn test_mismatching_file_is_quarantined() {
+    let url = spawn_test_server(|request, stream| {
+        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\n");
+        if request.starts_with(b"GET") {
+            let _ = stream.write_all(b"hello");
+        }
+    });
+
+    let temp_dir = create_temp_dir();
+    let path = temp_dir.path().join("file.txt");
+    let quarantine = temp_dir.path().join("quarantine");
+    let mut hasher = Algorithm::Sha256.hasher();
+    hasher.update(b"hello");
+    let download = Download {
+        checksum: Some(hasher.finalize()),
+        ..Download::try_from(format!("{}/file.txt", url).as_str()).unwrap()
+    };
+
+    // Download the file over a corrupted one, returning the mismatch summary.
+    let redownload = |policy: MismatchPolicy| {
+        std::fs::write(&path, b"stale").unwrap();
+        let mismatches = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let reported = mismatches.clone();
+        let downloader = DownloaderBuilder::hidden()
+            .directory(temp_dir.path().to_path_buf())
+            .mismatch_policy(policy)
+            .on_complete(move |summary| {
+                if let Status::HashMismatch(_) = summary.status() {
+                    reported.lock().unwrap().push(summary.clone());
+                }
+            })
+            .build();
+        let download = download.clone();
+        async move {
+            let summaries = downloader.download(&[download], None).await;
+            assert_eq!(summaries[0].status(), &Status::Success);
+            let mismatch = mismatches.lock().unwrap().pop().unwrap();
+            mismatch
+        }
+    };
+
+    assert_eq!(
+        DownloaderBuilder::new().build().mismatch_policy(),
+        &MismatchPolicy::Delete
+    );
+    let mismatch = redownload(MismatchPolicy::Delete).await;
+    assert_eq!(mismatch.quarantined(), None);
+    assert_eq!(std::fs::read(&path).unwrap(), b"hello");
+
+    let mismatch = redownload(MismatchPolicy::Quarantine(quarantine.clone())).await;
+    let moved = mismatch.quarantined().unwrap().to_path_buf();
+    assert_eq!(moved.parent(), Some(quarantine.as_path()));
+    assert!(moved.file_name().unwrap().to_string_lossy().ends_with("-file.txt"));
+    assert_eq!(std::fs::read(&moved).unwrap(), b"stale");
+    assert!(format!("{:?}", mismatch.status()).contains("quarantine"));
+    assert_eq!(std::fs::read(&path).unwrap(), b"hello");
+
+    let mismatch = redownload(MismatchPolicy::Rename).await;
+    let bad = temp_dir.path().join("file.txt.bad");
+    assert_eq!(mismatch.quarantined(), Some(bad.as_path()));
+    assert_eq!(std::fs::read(&bad).unwrap(), b"stale");
+    assert_eq!(std::fs::read(&path).unwrap(), b"hello");
+}
+
+#[tokio::test]
+async fn test_resumed_download_is_hashed() {
+    let url = spawn_test_server(|request, stream| {
+        let request = String::from_utf8_lossy(request).to_lowercase();
+        if request.contains("range: bytes=5-") {
+            let _ = stream.write_all(
+                b"HTTP/1.1 206 Partial Content\r\ncontent-length: 6\r\n\
+                  content-range: bytes 5-10/11\r\n\r\n world",
+            );
+            return;
+        }
+        let _ = stream.write_all(
+            b"HTTP/1.1 200 OK\r\naccept-ranges: bytes\r\ncontent-length: 11\r\n\r\n",
+        );
+        if request.starts_with("get") {
+            let _ = stream.write_all(b"hello world");
+        }
+    });
+
+    let temp_dir = create_temp_dir();
+    std::fs::write(temp_dir.path().join("file.txt.part"), b"hello").unwrap();
+    let mut hasher = Algorithm::Sha256.hasher();
+    hasher.update(b"hello world");
+    let expected = hasher.finalize();
+    let download = Download {
+        checksum: Some(expected.clone()),
+        ..Download::try_from(format!("{}/file.txt", url).as_str()).unwrap()
+    };
+
+    let downloader = DownloaderBuilder::hidden()
+        .directory(temp_dir.path().to_path_buf())
+        .build();
+    let summaries = downloader.download(&[download], None).await;
+    assert_eq!(summaries[0].status(), &Status::Success);
+    assert_eq!(summaries[0].resume_outcome(), ResumeOutcome::Honored);
+    assert_eq!(summaries[0].checksum(), Some(&expected));
+    assert_eq!(std::fs::read(temp_dir.path().join("file.txt")).unwrap(), b"hello world");
+}
+
+#[tokio::test]
+async fn test_downloaded_file_is_verified_against_headers() {
+    let url = spawn_test_server(|request, stream| {
+        let digest: &[u8] = match request.starts_with(b"GET /bad.txt") {
+            // The MD5 of `test`.
+            true => b"content-md5: CY9rzUYh03PK3k6DJie09g==\r\n",
+            false => b"repr-digest: sha-256=:LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=:\r\n",
+        };
+        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\n");
+        let _ = stream.write_all(digest);
+        let _ = stream.write_all(b"\r\nhello");
+    });
+
+    let temp_dir = create_temp_dir();
+    let good = Download::try_from(format!("{}/good.txt", url).as_str()).unwrap();
+    let bad = Download::try_from(format!("{}/bad.txt", url).as_str()).unwrap();
+    let builder = || {
+        DownloaderBuilder::hidden()
+            .directory(temp_dir.path().to_path_buf())
+            .overwrite(true)
+    };
+
+    // The headers are ignored unless asked otherwise.
+    let downloader = builder().build();
+    assert!(!downloader.checksums_from_response());
+    let summaries = downloader.download(std::slice::from_ref(&bad), None).await;
+    assert_eq!(summaries[0].status(), &Status::Success);
+    assert_eq!(summaries[0].checksum_source(), None);
+    std::fs::remove_file(temp_dir.path().join("bad.txt")).unwrap();
+
+    let downloader = builder().checksums_from_response(true).build();
+    let summaries = downloader.download(&[good.clone(), bad], None).await;
+    let good_summary = summaries.iter().find(|s| s.filename() == "good.txt").unwrap();
+    assert_eq!(good_summary.status(), &Status::Success);
+    assert_eq!(good_summary.checksum_source(), Some(ChecksumSource::ReprDigest));
+    assert_eq!(good_summary.checksum(), good_summary.expected_checksum());
+    let bad_summary = summaries.iter().find(|s| s.filename() == "bad.txt").unwrap();
+    assert_eq!(
+        bad_summary.status(),
+        &Status::HashMismatch(
+            "expected md5:098f6bcd4621d373cade4e832627b4f6 from Content-MD5".into()
+        )
+    );
+    assert!(!temp_dir.path().join("bad.txt").exists());
+
+    // The hash of the download takes precedence.
+    let mut hasher = Algorithm::Crc32.hasher();
+    hasher.update(b"hello");
+    let own = Download {
+        checksum: Some(hasher.finalize()),
+        ..good
+    };
+    let summaries = downloader.download(&[own], None).await;
+    assert_eq!(summaries[0].status(), &Status::Success);
+    assert_eq!(summaries[0].checksum_source(), Some(ChecksumSource::Download));
+    assert_eq!(summaries[0].expected_checksum().unwrap().algorithm, Algorithm::Crc32);
+}
+
+#[tokio::test]
+async fn test_audit() {
+    let temp_dir = create_temp_dir();
+    std::fs::write(temp_dir.path().join("good.txt"), b"hello").unwrap();
+    std::fs::write(temp_dir.path().join("bad.txt"), b"stale").unwrap();
+    std::fs::write(temp_dir.path().join("unknown.txt"), b"hello").unwrap();
+
+    let mut hasher = Algorithm::Sha256.hasher();
+    hasher.update(b"hello");
+    let checksum = hasher.finalize();
+    // Nothing listens on port 9, the network is never used.
+    let download = |name: &str, checksum: Option<Checksum>| Download {
+        checksum,
+        ..Download::try_from(format!("http://127.0.0.1:9/{}", name).as_str()).unwrap()
+    };
+    let downloads = [
+        download("good.txt", Some(checksum.clone())),
+        download("bad.txt", Some(checksum.clone())),
+        download("missing.txt", Some(checksum.clone())),
+        download("unknown.txt", None),
+    ];
+
+    let downloader = DownloaderBuilder::hidden()
+        .directory(temp_dir.path().to_path_buf())
+        .build();
+    let summaries = downloader.audit(&downloads).await;
+    let statuses = summaries.iter().map(|s| s.status().clone()).collect::<Vec<_>>();
+    assert_eq!(
+        statuses,
+        [
+            Status::Verified,
+            Status::HashMismatch(format!("expected {}", checksum)),
+            Status::Missing,
+            Status::Skipped("No hash to verify".into()),
+        ]
+    );
+    assert_eq!(summaries[0].size(), 5);
+    assert_eq!(summaries[0].checksum_source(), Some(ChecksumSource::Download));
+
+    // Mismatching files are left alone.
+    assert!(temp_dir.path().join("bad.txt").exists());
+}
+
+#[tokio::test]
+async fn test_sync_directory() {
+    static UNCHANGED_GETS: atomic::AtomicUsize = atomic::AtomicUsize::new(0);
+    let url = spawn_test_server(|request, stream| {
+        let request = String::from_utf8_lossy(request);
+        if request.contains(" /missing.txt ") {
+            let _ = stream.write_all(b"HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\n\r\n");
+            return;
+        }
+        if request.starts_with("GET /unchanged.txt") {
+            UNCHANGED_GETS.fetch_add(1, atomic::Ordering::SeqCst);
+        }
+        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\n");
+        if request.starts_with("GET") {
+            let _ = stream.write_all(b"hello");
+        }
+    });
+
+    let temp_dir = create_temp_dir();
+    let directory = temp_dir.path().join("assets");
+    std::fs::create_dir_all(directory.join("old")).unwrap();
+    std::fs::write(directory.join("unchanged.txt"), b"hello").unwrap();
+    std::fs::write(directory.join("updated.txt"), b"stale").unwrap();
+    std::fs::write(directory.join("extra.txt"), b"extra").unwrap();
+    std::fs::write(directory.join("old/extra.bin"), b"extra").unwrap();
+
+    let mut hasher = Algorithm::Sha256.hasher();
+    hasher.update(b"hello");
+    let checksum = hasher.finalize();
+    let downloads = ["unchanged.txt", "updated.txt", "added.txt", "missing.txt"]
+        .map(|name| Download {
+            checksum: Some(checksum.clone()),
+            ..Download::try_from(format!("{}/{}", url, name).as_str()).unwrap()
+        });
+
+    let downloader = DownloaderBuilder::hidden()
+        .retries(0)
+        .mismatch_policy(MismatchPolicy::Rename)
+        .build();
+    let report = downloader.sync(&downloads, &directory, false, None).await;
+    let names = |summaries: &[trauma::download::Summary]| {
+        summaries.iter().map(|s| s.filename().to_string()).collect::<Vec<_>>()
+    };
+    assert_eq!(names(&report.unchanged), ["unchanged.txt"]);
+    assert_eq!(names(&report.updated), ["updated.txt"]);
+    assert_eq!(names(&report.added), ["added.txt"]);
+    assert_eq!(names(&report.failed), ["missing.txt"]);
+    assert!(report.removed.is_empty());
+    assert!(!report.is_success());
+    assert_eq!(UNCHANGED_GETS.load(atomic::Ordering::SeqCst), 0);
+    assert_eq!(std::fs::read(directory.join("updated.txt")).unwrap(), b"hello");
+    assert!(directory.join("extra.txt").exists());
+
+    // Outdated files are set aside according to the mismatch policy.
+    let bad = directory.join("updated.txt.bad");
+    assert_eq!(report.updated[0].quarantined(), Some(bad.as_path()));
+    assert_eq!(std::fs::read(&bad).unwrap(), b"stale");
+
+    // Only the files of the manifest are kept.
+    let report = downloader.sync(&downloads[..3], &directory, true, None).await;
+    assert_eq!(report.unchanged.len(), 3);
+    assert!(report.is_success());
+    assert_eq!(
+        report.removed,
+        [std::path::PathBuf::from("extra.txt"), std::path::PathBuf::from("old/extra.bin")]
+    );
+    assert!(!directory.join("extra.txt").exists());
+    assert!(directory.join("added.txt").exists());
+    assert!(bad.exists());
+}
+
+#[tokio::test]
+async fn test_sync_keeps_quarantined_files() {
+    let url = spawn_test_server(|request, stream| {
+        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\n");
+        if request.starts_with(b"GET") {
+            let _ = stream.write_all(b"hello");
+        }
+    });
+
+    let temp_dir = create_temp_dir();
+    let directory = temp_dir.path().join("assets");
+    let quarantine = directory.join("quarantine");
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(directory.join("file.txt"), b"stale").unwrap();
+    std::fs::write(directory.join("file.txt.trauma.json.tmp"), b"{").unwrap();
+    std::fs::write(directory.join("extra.txt"), b"extra").unwrap();
+
+    let mut hasher = Algorithm::Sha256.hasher();
+    hasher.update(b"hello");
+    let download = Download {
+        checksum: Some(hasher.finalize()),
+        ..Download::try_from(format!("{}/file.txt", url).as_str()).unwrap()
+    };
+    let downloader = DownloaderBuilder::hidden()
+        .mismatch_policy(MismatchPolicy::Quarantine(quarantine.clone()))
+        .build();
+    let report = downloader.sync(&[download], &directory, true, None).await;
+
+    // Only the file outside of the manifest is pruned.
+    assert!(report.is_success());
+    assert_eq!(report.removed, [std::path::PathBuf::from("extra.txt")]);
+    let moved = report.updated[0].quarantined().unwrap();
+    assert!(moved.starts_with(&quarantine));
+    assert_eq!(std::fs::read(moved).unwrap(), b"stale");
+    assert!(directory.join("file.txt.trauma.json.tmp").exists());
+    assert_eq!(std::fs::read(directory.join("file.txt")).unwrap(), b"hello");
+}
+
+#[tokio::test]
+async fn test_range_responses() {
+    let url = spawn_test_server(|request, stream| {
+        let content = test_content(0..10);
+        let request_line = String::from_utf8_lossy(request);
+        let path = request_line.split(' ').nth(1).unwrap_or_default();
+        if request.starts_with(b"HEAD") || requested_range(request).is_none() {
+            let _ = stream.write_all(
+                b"HTTP/1.1 200 OK\r\naccept-ranges: bytes\r\ncontent-length: 10\r\n\r\n",
+            );
+            if request.starts_with(b"GET") {
+                let _ = stream.write_all(&content);
+            }
+            return;
+        }
+        match path {
+            // The range is ignored, the whole file is sent.
+            "/ignored.bin" => {
+                let _ = stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 10\r\n\r\n");
+                let _ = stream.write_all(&content);
+            }
+            // The range sent is not the one requested.
+            "/shifted.bin" => {
+                let _ = stream.write_all(
+                    b"HTTP/1.1 206 Partial Content\r\ncontent-length: 10\r\n\
+                      content-range: bytes 0-9/10\r\n\r\n",
+                );
+                let _ = stream.write_all(&content);
+            }
+            // Nothing is left to send.
+            _ => {
+                let _ = stream.write_all(
+                    b"HTTP/1.1 416 Range Not Satisfiable\r\ncontent-length: 0\r\n\
+                      content-range: bytes */10\r\n\r\n",
+                );
+            }
+        }
+    });
+
+    let temp_dir = create_temp_dir();
+    let content = test_content(0..10);
+    let file = |name: &str| temp_dir.path().join(name);
+    std::fs::write(file("ignored.bin.part"), b"xxxx").unwrap();
+    std::fs::write(file("shifted.bin.part"), &content[..4]).unwrap();
+    std::fs::write(file("complete.bin.part"), &content).unwrap();
+    let downloads = ["ignored.bin", "shifted.bin", "complete.bin"]
+        .map(|name| Download::try_from(format!("{}/{}", url, name).as_str()).unwrap());
+
+    let downloader = DownloaderBuilder::hidden()
+        .directory(temp_dir.path().to_path_buf())
+        .retries(0)
+        .build();
+    let summaries = downloader.download(&downloads, None).await;
+    let summary = |name: &str| summaries.iter().find(|s| s.filename() == name).unwrap();
+
+    // A full response restarts the download from scratch.
+    assert_eq!(summary("ignored.bin").status(), &Status::Success);
+    assert_eq!(summary("ignored.bin").resume_outcome(), ResumeOutcome::Restarted);
+    assert_eq!(std::fs::read(file("ignored.bin")).unwrap(), content);
+
+    // A range that does not continue the partial file is not appended to it.
+    match summary("shifted.bin").status() {
+        Status::Fail(e) => assert!(e.contains("bytes 0-9/10"), "{}", e),
+        status => panic!("unexpected status {:?}", status),
+    }
+    assert!(!file("shifted.bin").exists());
+    assert_eq!(std::fs::read(file("shifted.bin.part")).unwrap(), &content[..4]);
+
+    // A complete partial file is moved into place.
+    assert_eq!(
+        summary("complete.bin").status(),
+        &Status::Skipped("the file was already fully downloaded".into())
+    );
+    assert_eq!(std::fs::read(file("complete.bin")).unwrap(), content);
+    assert!(!file("complete.bin.part").exists());
+}
+
+#[tokio::test]
+async fn test_throttled_download_is_cancelled_promptly() {
+    let url = spawn_test_server(|request, stream| {
+        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 65536\r\n\r\n");
+        if request.starts_with(b"GET") {
+            let _ = stream.write_all(&test_content(0..65536));
+        }
+    });
+
+    // At a byte per second, the first chunk alone takes hours to pace.
+    let temp_dir = create_temp_dir();
+    let downloader = DownloaderBuilder::hidden()
+        .directory(temp_dir.path().to_path_buf())
+        .max_bandwidth(1)
+        .build();
+    let downloads = vec![Download::try_from(format!("{}/file.bin", url).as_str()).unwrap()];
+    let (handle, batch) = downloader.start(&downloads, None);
+    let control = async {
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        handle.pause();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        handle.cancel();
+    };
+
+    let (summaries, ()) = tokio::time::timeout(Duration::from_secs(5), async {
+        tokio::join!(batch, control)
+    })
+    .await
+    .expect("the throttled download should stop once cancelled");
+    assert_eq!(summaries[0].status(), &Status::Cancelled);
+
+    // The chunk received before stopping is kept for a later resume.
+    let part = std::fs::read(temp_dir.path().join("file.bin.part")).unwrap();
+    assert!(!part.is_empty());
+    assert_eq!(part, test_content(0..part.len() as u64));
+}
+
+#[tokio::test]
+async fn test_connections_per_host_include_segments() {
+    static OPEN: atomic::AtomicUsize = atomic::AtomicUsize::new(0);
+    static PEAK: atomic::AtomicUsize = atomic::AtomicUsize::new(0);
+    let url = spawn_test_server(|request, stream| {
+        let size = 2 * MIN_SEGMENT_SIZE;
+        let open = OPEN.fetch_add(1, atomic::Ordering::SeqCst) + 1;
+        PEAK.fetch_max(open, atomic::Ordering::SeqCst);
+        let (start, end) = match requested_range(request) {
+            Some((start, end)) => (start, end.map_or(size, |end| end + 1)),
+            None => (0, size),
+        };
+        let head = match requested_range(request) {
+            Some(_) => format!(
+                "HTTP/1.1 206 Partial Content\r\ncontent-length: {}\r\n\
+                 content-range: bytes {}-{}/{}\r\n\r\n",
+                end - start,
+                start,
+                end - 1,
+                size
+            ),
+            None => format!(
+                "HTTP/1.1 200 OK\r\naccept-ranges: bytes\r\ncontent-length: {}\r\n\r\n",
+                size
+            ),
+        };
+
+        // Keep the requests for the body open long enough to overlap.
+        if request.starts_with(b"GET") {
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        OPEN.fetch_sub(1, atomic::Ordering::SeqCst);
+        let _ = stream.write_all(head.as_bytes());
+        if request.starts_with(b"GET") {
+            let _ = stream.write_all(&test_content(start..end));
+        }
+    });
+
+    let temp_dir = create_temp_dir();
+    let downloads = ["a.bin", "b.bin", "c.bin"]
+        .map(|name| Download::try_from(format!("{}/{}", url, name).as_str()).unwrap());
+    let downloader = DownloaderBuilder::hidden()
+        .directory(temp_dir.path().to_path_buf())
+        .max_connections_per_host(2)
+        .segments(2)
+        .build();
+    let summaries = downloader.download(&downloads, None).await;
+
+    // Two downloads at once leave no connection to spare for their segments.
+    assert!(summaries.iter().all(|s| s.status() == &Status::Success));
+    assert_eq!(PEAK.load(atomic::Ordering::SeqCst), 2);
+    for name in ["a.bin", "b.bin", "c.bin"] {
+        let content = std::fs::read(temp_dir.path().join(name)).unwrap();
+        assert!(content == test_content(0..2 * MIN_SEGMENT_SIZE));
+    }
+}
+
+#[tokio::test]
+async fn test_host_delay_spaces_requests() {
+    static ARRIVALS: std::sync::Mutex<Vec<Instant>> = std::sync::Mutex::new(Vec::new());
+    let url = spawn_test_server(|request, stream| {
+        ARRIVALS.lock().unwrap().push(Instant::now());
+        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 4\r\n\r\n");
+        if request.starts_with(b"GET") {
+            let _ = stream.write_all(b"data");
+        }
+    });
+
+    let delay = Duration::from_millis(150);
+    let temp_dir = create_temp_dir();
+    let downloads = ["a.bin", "b.bin"]
+        .map(|name| Download::try_from(format!("{}/{}", url, name).as_str()).unwrap());
+    let downloader = DownloaderBuilder::hidden()
+        .directory(temp_dir.path().to_path_buf())
+        .host_delay(delay)
+        .build();
+    let summaries = downloader.download(&downloads, None).await;
+    assert!(summaries.iter().all(|s| s.status() == &Status::Success));
+
+    // Every request of both downloads waited for the previous one.
+    let mut arrivals = ARRIVALS.lock().unwrap().clone();
+    arrivals.sort();
+    assert!(arrivals.len() >= 4);
+    for pair in arrivals.windows(2) {
+        let gap = pair[1] - pair[0];
+        assert!(gap >= delay - Duration::from_millis(20), "{:?}", gap);
+    }
+}
+
+#[tokio::test]
+async fn test_segments_are_conditional() {
+    static UNCONDITIONAL: atomic::AtomicUsize = atomic::AtomicUsize::new(0);
+    // A server whose file changes after the first segment, ignoring `If-Range`.
+    let url = spawn_test_server(|request, stream| {
+        let size = 2 * MIN_SEGMENT_SIZE;
+        let text = String::from_utf8_lossy(request).to_ascii_lowercase();
+        let Some((start, end)) = requested_range(request) else {
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\naccept-ranges: bytes\r\netag: \"v1\"\r\n\
+                 content-length: {}\r\n\r\n",
+                size
+            );
+            return;
+        };
+        if !text.contains("if-range: \"v1\"") {
+            UNCONDITIONAL.fetch_add(1, atomic::Ordering::SeqCst);
+        }
+        let etag = match text.starts_with("get /changed.bin") && start > 0 {
+            true => "\"v2\"",
+            false => "\"v1\"",
+        };
+        let end = end.map_or(size, |end| end + 1);
+        let _ = write!(
+            stream,
+            "HTTP/1.1 206 Partial Content\r\netag: {}\r\ncontent-length: {}\r\n\
+             content-range: bytes {}-{}/{}\r\n\r\n",
+            etag,
+            end - start,
+            start,
+            end - 1,
+            size
+        );
+        let _ = stream.write_all(&test_content(start..end));
+    });
+
+    let temp_dir = create_temp_dir();
+    let downloads = ["same.bin", "changed.bin"]
+        .map(|name| Download::try_from(format!("{}/{}", url, name).as_str()).unwrap());
+    let downloader = DownloaderBuilder::hidden()
+        .directory(temp_dir.path().to_path_buf())
+        .segments(2)
+        .retries(0)
+        .build();
+    let summaries = downloader.download(&downloads, None).await;
+    let summary = |name: &str| summaries.iter().find(|s| s.filename() == name).unwrap();
+
+    // Every segment is requested for the version of the file that was probed.
+    assert_eq!(UNCONDITIONAL.load(atomic::Ordering::SeqCst), 0);
+    assert_eq!(summary("same.bin").status(), &Status::Success);
+
+    // Segments of different versions are never stitched together.
+    match summary("changed.bin").status() {
+        Status::Fail(e) => assert!(e.contains("changed"), "{}", e),
+        status => panic!("unexpected status {:?}", status),
+    }
+    assert!(!temp_dir.path().join("changed.bin").exists());
+    assert!(!temp_dir.path().join("changed.bin.part").exists());
+}
+
+#[tokio::test]
+async fn test_resume_validators_are_journaled() {
+    const SIZE: u64 = 64;
+    static RANGES: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+    let url = spawn_test_server(|request, stream| {
+        let head = "etag: \"v1\"\r\naccept-ranges: bytes";
+        let Some((start, _)) = requested_range(request) else {
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\n{}\r\ncontent-length: {}\r\n\r\n",
+                head, SIZE
+            );
+            // Cut the first transfer short.
+            if request.starts_with(b"GET") {
+                let _ = stream.write_all(&test_content(0..16));
+            }
+            return;
+        };
+        let text = String::from_utf8_lossy(request).to_lowercase();
+        let if_range = text.lines().find(|line| line.starts_with("if-range:"));
+        RANGES.lock().unwrap().push(if_range.unwrap_or_default().to_string());
+        let _ = write!(
+            stream,
+            "HTTP/1.1 206 Partial Content\r\n{}\r\ncontent-length: {}\r\n\
+             content-range: bytes {}-{}/{}\r\n\r\n",
+            head,
+            SIZE - start,
+            start,
+            SIZE - 1,
+            SIZE
+        );
+        let _ = stream.write_all(&test_content(start..SIZE));
+    });
+
+    let temp_dir = create_temp_dir();
+    let path = temp_dir.path().join("file.bin");
+    let download = Download::try_from(format!("{}/file.bin", url).as_str()).unwrap();
+    let downloader = DownloaderBuilder::hidden()
+        .directory(temp_dir.path().to_path_buf())
+        .retries(0)
+        .recovery_attempts(0)
+        .build();
+
+    // Even without journaling, the validators are kept in the journal only.
+    let summaries = downloader.download(std::slice::from_ref(&download), None).await;
+    assert!(matches!(summaries[0].status(), Status::Fail(_)));
+    let journal = Journal::load(&journal_path(&path)).await.unwrap();
+    assert_eq!(journal.validator().unwrap(), "\"v1\"");
+    assert!(!temp_dir.path().join("file.bin.part.validator").exists());
+
+    // The resume is conditional on the journaled validator.
+    let summaries = downloader.download(&[download], None).await;
+    assert_eq!(summaries[0].status(), &Status::Success);
+    assert_eq!(summaries[0].resume_outcome(), ResumeOutcome::Honored);
+    assert_eq!(*RANGES.lock().unwrap(), vec!["if-range: \"v1\"".to_string()]);
+    assert!(std::fs::read(&path).unwrap() == test_content(0..SIZE));
+    assert!(!journal_path(&path).exists());
+}
+
+#[tokio::test]
+async fn test_segmented_download() {
+    const SIZE: u64 = 4 * MIN_SEGMENT_SIZE;
+    static FAIL_ONCE: atomic::AtomicBool = atomic::AtomicBool::new(true);
+    static SERVED: atomic::AtomicU64 = atomic::AtomicU64::new(0);
+    let url = spawn_test_server(|request, stream| {
+        let Some((start, end)) = requested_range(request) else {
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\naccept-ranges: bytes\r\ncontent-length: {}\r\n\r\n",
+                SIZE
+            );
+            return;
+        };
+        let end = end.unwrap_or(SIZE - 1);
+        let _ = write!(
+            stream,
+            "HTTP/1.1 206 Partial Content\r\ncontent-length: {}\r\n\
+             content-range: bytes {}-{}/{}\r\n\r\n",
+            end + 1 - start,
+            start,
+            end,
+            SIZE
+        );
+        let mut body = test_content(start..end + 1);
+        // Cut the third segment short once.
+        if start == 2 * MIN_SEGMENT_SIZE && FAIL_ONCE.swap(false, atomic::Ordering::SeqCst) {
+            body.truncate(body.len() / 2);
+        }
+        SERVED.fetch_add(body.len() as u64, atomic::Ordering::SeqCst);
+        let _ = stream.write_all(&body);
+    });
+
+    let temp_dir = create_temp_dir();
+    let path = temp_dir.path().join("file.bin");
+    let content = test_content(0..SIZE);
+    let mut hasher = Algorithm::Sha256.hasher();
+    hasher.update(&content);
+    let download = Download {
+        checksum: Some(hasher.finalize()),
+        ..Download::try_from(format!("{}/file.bin", url).as_str()).unwrap()
+    };
+    let downloader = DownloaderBuilder::hidden()
+        .directory(temp_dir.path().to_path_buf())
+        .segments(4)
+        .journal(true)
+        .retries(0)
+        .build();
+
+    // The failed segment leaves a journal of the written segments behind.
+    let summaries = downloader.download(std::slice::from_ref(&download), None).await;
+    assert!(matches!(summaries[0].status(), Status::Fail(_)));
+    assert!(!path.exists());
+    let journal = Journal::load(&journal_path(&path)).await.unwrap();
+    assert_eq!(journal.total_length, Some(SIZE));
+    assert_eq!(
+        journal.segments.iter().map(|s| s.segment()).collect::<Vec<_>>(),
+        split_into_segments(SIZE, 4)
+    );
+    assert!(!journal.segments[2].is_complete());
+    assert_eq!(journal.segments.iter().filter(|s| s.is_complete()).count(), 3);
+
+    // Only the missing segment is downloaded again.
+    SERVED.store(0, atomic::Ordering::SeqCst);
+    let summaries = downloader.download(&[download], None).await;
+    assert_eq!(summaries[0].status(), &Status::Success);
+    assert_eq!(summaries[0].size(), SIZE);
+    assert_eq!(summaries[0].resume_outcome(), ResumeOutcome::Honored);
+    assert_eq!(SERVED.load(atomic::Ordering::SeqCst), MIN_SEGMENT_SIZE);
+    assert!(std::fs::read(&path).unwrap() == content);
+    assert!(!journal_path(&path).exists());
+}
+
+#[tokio::test]
+async fn test_downloads_start_in_scheduled_order() {
+    static STARTED: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+    let url = spawn_test_server(|request, stream| {
+        let request_line = String::from_utf8_lossy(request);
+        let path = request_line.split(' ').nth(1).unwrap_or_default();
+        let name = path.rsplit('/').next().unwrap_or_default();
+        if request.starts_with(b"GET") {
+            STARTED.lock().unwrap().push(path.to_string());
+        }
+
+        // The size of `d` is unknown until its body ends.
+        let size = match name {
+            "a" => 10,
+            "b" => 1000,
+            "c" => 1,
+            _ => {
+                let _ = stream.write_all(b"HTTP/1.1 200 OK\r\n\r\n");
+                if request.starts_with(b"GET") {
+                    let _ = stream.write_all(b"d");
+                }
+                return;
+            }
+        };
+        let _ = write!(stream, "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n", size);
+        if request.starts_with(b"GET") {
+            let _ = stream.write_all(&test_content(0..size));
+        }
+    });
+
+    let temp_dir = create_temp_dir();
+    let downloads = |directory: &str, priorities: [i32; 4]| {
+        ["a", "b", "c", "d"]
+            .into_iter()
+            .zip(priorities)
+            .map(|(name, priority)| Download {
+                priority,
+                ..Download::try_from(format!("{}/{}/{}", url, directory, name).as_str())
+                    .unwrap()
+            })
+            .collect::<Vec<_>>()
+    };
+    let started = |directory: &str| {
+        STARTED
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|path| path.strip_prefix(&format!("/{}/", directory)))
+            .map(String::from)
+            .collect::<Vec<_>>()
+    };
+    let downloader = |strategy| {
+        DownloaderBuilder::hidden()
+            .directory(temp_dir.path().to_path_buf())
+            .overwrite(true)
+            .concurrent_downloads(1)
+            .scheduling(strategy)
+            .build()
+    };
+
+    // Higher priorities start first, equal ones keep the queue order.
+    let summaries = downloader(SchedulingStrategy::Priority)
+        .download(&downloads("priority", [0, 5, 0, 10]), None)
+        .await;
+    assert!(summaries.iter().all(|s| s.status() == &Status::Success));
+    assert_eq!(started("priority"), ["d", "b", "a", "c"]);
+
+    // Equal priorities start with the smallest download, unknown sizes last.
+    let summaries = downloader(SchedulingStrategy::SmallestFirst)
+        .download(&downloads("smallest", [0; 4]), None)
+        .await;
+    assert!(summaries.iter().all(|s| s.status() == &Status::Success));
+    assert_eq!(started("smallest"), ["c", "a", "b", "d"]);
+}
+
+#[test]
+fn test_split_into_segments() {
+    let segments = split_into_segments(10, 3);
+    assert_eq!(
+        segments,
+        vec![
+            Segment { start: 0, end: 3 },
+            Segment { start: 4, end: 6 },
+            Segment { start: 7, end: 9 },
+        ]
+    );
+    assert_eq!(segments.iter().map(Segment::len).sum::<u64>(), 10);
+
+    // Never more segments than bytes, and nothing to split for empty files.
+    assert_eq!(split_into_segments(2, 8).len(), 2);
+    assert!(split_into_segments(0, 4).is_empty());
+}
+
+#[test]
+fn test_segment_count() {
+    assert_eq!(segment_count(10 * MIN_SEGMENT_SIZE, 4), 4);
+    assert_eq!(segment_count(2 * MIN_SEGMENT_SIZE, 4), 2);
+    assert_eq!(segment_count(MIN_SEGMENT_SIZE / 2, 4), 1);
+}
+
+#[test]
+fn test_segment_range_header() {
+    let segment = Segment { start: 100, end: 199 };
+    assert_eq!(segment.range_header(0), "bytes=100-199");
+    assert_eq!(segment.range_header(50), "bytes=150-199");
+}
@@ -1,7 +1,10 @@
 //! Tests for HTTP module functionality.
 
 use reqwest::header::{HeaderValue, USER_AGENT};
+use reqwest::Url;
+use std::time::Duration;
 use trauma::http::client::{create_http_client, HttpClientConfig};
+use trauma::http::politeness::{host_key, HostDelay};
 
 mod common;
 use common::helpers::*;
@@ -12,6 +15,21 @@ fn test_default_config() {
     assert_eq!(config.retries, 3);
     assert!(config.proxy.is_none());
     assert!(config.headers.is_none());
+    assert!(config.host_delay.is_none());
+    assert!(config.connect_timeout.is_none());
+    assert!(config.read_timeout.is_none());
+    assert!(config.timeout.is_none());
+}
+
+#[test]
+fn test_create_http_client_with_timeouts() {
+    let config = HttpClientConfig {
+        connect_timeout: Some(Duration::from_secs(5)),
+        read_timeout: Some(Duration::from_secs(10)),
+        timeout: Some(Duration::from_secs(60)),
+        ..Default::default()
+    };
+    assert!(create_http_client(config).is_ok());
 }
 
 #[test]
@@ -42,6 +60,10 @@ fn test_http_config_with_custom_headers() {
         retries: 2,
         proxy: None,
         headers: Some(headers.clone()),
+        host_delay: None,
+        connect_timeout: None,
+        read_timeout: None,
+        timeout: None,
     };
 
     assert_eq!(config.retries, 2);
@@ -50,4 +72,28 @@ fn test_http_config_with_custom_headers() {
         config.headers.unwrap().get(USER_AGENT),
         Some(&HeaderValue::from_static("custom-test-agent"))
     );
-}
\ No newline at end of file
+}
+#[test]
+fn test_host_delay_spaces_requests_per_host() {
+    let delay = Duration::from_secs(10);
+    let middleware = HostDelay::new(delay);
+    let first = Url::parse("https://example.com/a").unwrap();
+    let second = Url::parse("https://example.com/b").unwrap();
+    let other = Url::parse("https://example.org/a").unwrap();
+
+    assert!(middleware.reserve(&first).is_zero());
+    let wait = middleware.reserve(&second);
+    assert!(wait > delay - Duration::from_secs(1) && wait <= delay);
+
+    // Other hosts are not delayed.
+    assert!(middleware.reserve(&other).is_zero());
+}
+
+#[test]
+fn test_host_key_includes_port() {
+    let url = Url::parse("https://example.com/file.zip").unwrap();
+    assert_eq!(host_key(&url), "example.com:443");
+
+    let url = Url::parse("http://example.com:8080/file.zip").unwrap();
+    assert_eq!(host_key(&url), "example.com:8080");
+}
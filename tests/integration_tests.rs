@@ -135,6 +135,10 @@ async fn test_http_client_integration() {
         retries: 2,
         proxy: None,
         headers: Some(headers.clone()),
+        host_delay: None,
+        connect_timeout: None,
+        read_timeout: None,
+        timeout: None,
     };
     
     // Test HTTP client creation
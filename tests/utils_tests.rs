@@ -1,6 +1,11 @@
 //! Tests for utils module functionality.
 
-use trauma::utils::{get_content_length, parse_content_range_total, extract_content_length};
+use reqwest::Url;
+use std::path::{Path, PathBuf};
+use trauma::utils::{
+    append_extension, extract_content_length, filename_from_url, get_content_length,
+    parse_content_disposition, parse_content_range_start, parse_content_range_total, part_path,
+};
 
 mod common;
 use common::helpers::*;
@@ -26,6 +31,17 @@ fn test_parse_content_range_total_edge_cases() {
     );
 }
 
+#[test]
+fn test_parse_content_range_start() {
+    assert_eq!(parse_content_range_start("bytes 0-1023/2048"), Some(0));
+    assert_eq!(parse_content_range_start("bytes 1024-2047/2048"), Some(1024));
+    assert_eq!(parse_content_range_start(" bytes 200-1023/* "), Some(200));
+    // Unsatisfied ranges and malformed headers have no start position.
+    assert_eq!(parse_content_range_start("bytes */2048"), None);
+    assert_eq!(parse_content_range_start("invalid"), None);
+    assert_eq!(parse_content_range_start(""), None);
+}
+
 #[tokio::test]
 async fn test_get_content_length_with_content_range() {
     // We can't easily create a Response directly, so we'll test the parsing function
@@ -64,4 +80,56 @@ async fn test_extract_content_length_no_fallback() {
             assert!(len > 0);
         }
     }
-}
\ No newline at end of file
+}
+#[test]
+fn test_part_path() {
+    assert_eq!(part_path(Path::new("file.zip")), PathBuf::from("file.zip.part"));
+    assert_eq!(
+        part_path(Path::new("output/nested/file")),
+        PathBuf::from("output/nested/file.part")
+    );
+}
+
+#[test]
+fn test_append_extension_keeps_existing_extension() {
+    assert_eq!(
+        append_extension(Path::new("archive.tar.gz"), "bad"),
+        PathBuf::from("archive.tar.gz.bad")
+    );
+}
+
+#[test]
+fn test_parse_content_disposition() {
+    let cases = [
+        ("attachment; filename=report.pdf", Some("report.pdf")),
+        (r#"attachment; filename="my report.pdf""#, Some("my report.pdf")),
+        (r#"attachment; filename="a \"quoted\" name.txt""#, Some(r#"a "quoted" name.txt"#)),
+        (r#"attachment; filename="semi;colon.txt""#, Some("semi;colon.txt")),
+        // The extended parameter wins, whatever its position.
+        ("attachment; filename*=UTF-8''na%C3%AFve.txt; filename=naive.txt", Some("naïve.txt")),
+        ("attachment; FILENAME*=iso-8859-1'en'caf%E9.txt", Some("café.txt")),
+        // Path components are stripped.
+        (r#"attachment; filename="../../etc/passwd""#, Some("passwd")),
+        (r#"attachment; filename="C:\\temp\\file.txt""#, Some("file.txt")),
+        ("attachment; filename=\"..\"", None),
+        ("inline", None),
+        ("attachment; filename*=unknown''file.txt", None),
+    ];
+    for (value, expected) in cases {
+        assert_eq!(
+            parse_content_disposition(value).as_deref(),
+            expected,
+            "{}",
+            value
+        );
+    }
+}
+
+#[test]
+fn test_filename_from_url() {
+    let url = Url::parse("https://example.com/releases/latest/app%20v1.tar.gz").unwrap();
+    assert_eq!(filename_from_url(&url).as_deref(), Some("app v1.tar.gz"));
+
+    let url = Url::parse("https://example.com/downloads/").unwrap();
+    assert_eq!(filename_from_url(&url), None);
+}